@@ -0,0 +1,2 @@
+#!/bin/sh
+cargo test --release -- --nocapture --ignored check_determinism
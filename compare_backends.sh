@@ -0,0 +1,2 @@
+#!/bin/sh
+cargo test --release -- --nocapture --test-threads=1 --ignored compare_precompile_backends
@@ -0,0 +1,2 @@
+#!/bin/sh
+cargo test --release -- --nocapture --test-threads=1 --ignored fuzz_precompile_backends
@@ -1,5 +1,5 @@
 #!/bin/sh
-for D in $(find ./vectors -mindepth 0 -maxdepth 3 -name '*.csv') ; do
+for D in $(find ./vectors -mindepth 0 -maxdepth 4 -name '*.csv') ; do
     rm $D ;
 done
 
@@ -0,0 +1,2 @@
+#!/bin/sh
+RAYON_NUM_THREADS=1 cargo test --release -- --nocapture --test-threads=1 --ignored search_worst_
@@ -0,0 +1,512 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use super::determinism::{InvalidVectorSet, VectorSet};
+use super::fuzzing::{self, Outcome};
+use super::generator;
+use super::helpers;
+use super::measurements::{self, TimingStats};
+use super::runners::{self, PrecompileError};
+
+pub const BACKEND_PRECOMPILES_ENV: &str = "BENCH_BACKEND_PRECOMPILES";
+pub const BACKEND_TRIES_ENV: &str = "BENCH_BACKEND_TRIES";
+pub const BACKENDS_OUTPUT_ENV: &str = "BENCH_BACKENDS_OUTPUT";
+
+pub const BACKENDS_FILE_NAME: &str = "backends.json";
+
+// Measuring a vector with one backend stops after this much time, so that Blake2f vectors of
+// millions of rounds are measured at least once but don't take minutes
+pub const MEASUREMENT_BUDGET_NS: u128 = 200_000_000;
+
+// Implementation of a precompile. Output is encoded as in the vectors, e.g. RIPEMD160 output
+// is padded to 32 bytes, so outputs of all backends and of the vectors can be compared
+pub struct Backend {
+    pub name: &'static str,
+    pub run: fn(&[u8]) -> Result<Vec<u8>, PrecompileError>
+}
+
+// The first backend is the one vectors are generated with
+pub fn backends(precompile: &str) -> Result<Vec<Backend>, &'static str> {
+    let backends = match precompile {
+        "sha256" => vec![
+            Backend { name: "parity-crypto", run: |input| Ok(runners::run_sha256(input).to_vec()) },
+            Backend { name: "sha2", run: run_sha256_sha2 }
+        ],
+        "ripemd" => vec![
+            Backend { name: "parity-crypto", run: |input| Ok(pad_ripemd_output(&runners::run_ripemd160(input))) },
+            Backend { name: "ripemd160", run: run_ripemd_ripemd160 }
+        ],
+        // no other implementation of ECRECOVER is available, the vectors are still checked
+        "ecrecover" => vec![
+            Backend { name: "parity-crypto", run: |input| Ok(generator::encode_ecrecover_output(&runners::run_ecrecover(input))) }
+        ],
+        // `eip_152::compress` the vectors are generated with dispatches to the AVX2 compression
+        // function if the CPU supports it and to the portable one otherwise
+        "blake2f" => {
+            let portable = Backend { name: "portable", run: |input| runners::run_blake2f_with(input, eip_152::portable::compress).map(|o| o.to_vec()) };
+            if avx2_available() {
+                vec![Backend { name: "avx2", run: run_blake2f_avx2 }, portable]
+            } else {
+                vec![portable]
+            }
+        },
+        "bnadd" => vec![
+            Backend { name: "bn", run: |input| runners::run_bn_add(input).map(|o| o.to_vec()) },
+            Backend { name: "substrate-bn", run: run_bn_add_substrate },
+            Backend { name: "arkworks", run: run_bn_add_arkworks }
+        ],
+        "bnmul" => vec![
+            Backend { name: "bn", run: |input| runners::run_bn_mul(input).map(|o| o.to_vec()) },
+            Backend { name: "substrate-bn", run: run_bn_mul_substrate },
+            Backend { name: "arkworks", run: run_bn_mul_arkworks }
+        ],
+        "bnpair" => vec![
+            Backend { name: "bn", run: |input| runners::run_bn_pair(input).map(|o| o.to_vec()) },
+            Backend { name: "substrate-bn", run: run_bn_pair_substrate },
+            Backend { name: "arkworks", run: run_bn_pair_arkworks }
+        ],
+        _ => {
+            return Err("unknown precompile");
+        }
+    };
+
+    Ok(backends)
+}
+
+// Zero padded or cut to `len` bytes, as BN254 addition and multiplication read their input
+fn pad_input(input: &[u8], len: usize) -> Vec<u8> {
+    let mut padded = vec![0u8; len];
+    let copied = std::cmp::min(input.len(), len);
+    padded[..copied].copy_from_slice(&input[..copied]);
+
+    padded
+}
+
+fn pad_ripemd_output(output: &[u8]) -> Vec<u8> {
+    let mut padded = vec![0u8; 12];
+    padded.extend_from_slice(output);
+
+    padded
+}
+
+pub fn run_sha256_sha2(input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    use sha2::{Digest, Sha256};
+
+    Ok(Sha256::digest(input).to_vec())
+}
+
+pub fn run_ripemd_ripemd160(input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    use ripemd160::{Digest, Ripemd160};
+
+    Ok(pad_ripemd_output(&Ripemd160::digest(input)))
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn avx2_available() -> bool {
+    is_x86_feature_detected!("avx2")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn avx2_available() -> bool {
+    false
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn compress_avx2(h: &mut [u64; 8], m: [u64; 16], t: [u64; 2], f: bool, rounds: usize) {
+    assert!(avx2_available());
+    // AVX2 support is checked above
+    unsafe {
+        eip_152::avx2::compress(h, m, t, f, rounds)
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub fn run_blake2f_avx2(input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    runners::run_blake2f_with(input, compress_avx2).map(|o| o.to_vec())
+}
+
+// Never listed as a backend, see `avx2_available`
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+pub fn run_blake2f_avx2(_input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    panic!("AVX2 is not available")
+}
+
+// Same checks as `helpers::read_point` and `helpers::read_g2_point`, but with substrate-bn from
+// crates.io instead of the `bn` revision the runners use
+fn read_g1_substrate(reader: &[u8]) -> Result<substrate_bn::G1, PrecompileError> {
+    use substrate_bn::{Fq, AffineG1, G1, Group};
+
+    let px = Fq::from_slice(&reader[0..32]).map_err(|_| PrecompileError::NotBelowModulus("Invalid point x coordinate"))?;
+    let py = Fq::from_slice(&reader[32..64]).map_err(|_| PrecompileError::NotBelowModulus("Invalid point y coordinate"))?;
+    if px.is_zero() && py.is_zero() {
+        return Ok(G1::zero());
+    }
+
+    Ok(AffineG1::new(px, py).map_err(|_| PrecompileError::NotOnCurve("Invalid curve point"))?.into())
+}
+
+fn read_g2_substrate(reader: &[u8]) -> Result<substrate_bn::G2, PrecompileError> {
+    use substrate_bn::{Fq, Fq2, Fr, G2, Group};
+
+    let x_imaginary = Fq::from_slice(&reader[0..32]).map_err(|_| PrecompileError::NotBelowModulus("Invalid b argument imaginary coeff x coordinate"))?;
+    let x_real = Fq::from_slice(&reader[32..64]).map_err(|_| PrecompileError::NotBelowModulus("Invalid b argument real coeff x coordinate"))?;
+    let y_imaginary = Fq::from_slice(&reader[64..96]).map_err(|_| PrecompileError::NotBelowModulus("Invalid b argument imaginary coeff y coordinate"))?;
+    let y_real = Fq::from_slice(&reader[96..128]).map_err(|_| PrecompileError::NotBelowModulus("Invalid b argument real coeff y coordinate"))?;
+
+    let x = Fq2::new(x_real, x_imaginary);
+    let y = Fq2::new(y_real, y_imaginary);
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2::zero());
+    }
+    if y * y != x * x * x + G2::b() {
+        return Err(PrecompileError::NotOnCurve("Invalid b argument - not on curve"));
+    }
+
+    let point = G2::new(x, y, Fq2::one());
+    if !(point * (-Fr::one()) + point).is_zero() {
+        return Err(PrecompileError::NotInSubgroup("Invalid b argument - not in subgroup"));
+    }
+
+    Ok(point)
+}
+
+fn encode_g1_substrate(point: substrate_bn::G1) -> Vec<u8> {
+    let mut output = vec![0u8; 64];
+    if let Some(affine) = substrate_bn::AffineG1::from_jacobian(point) {
+        affine.x().to_big_endian(&mut output[0..32]).expect("Cannot fail since 0..32 is 32-byte length");
+        affine.y().to_big_endian(&mut output[32..64]).expect("Cannot fail since 32..64 is 32-byte length");
+    }
+
+    output
+}
+
+pub fn run_bn_add_substrate(input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    let input = pad_input(input, 128);
+
+    let p1 = read_g1_substrate(&input[0..64])?;
+    let p2 = read_g1_substrate(&input[64..128])?;
+
+    Ok(encode_g1_substrate(p1 + p2))
+}
+
+pub fn run_bn_mul_substrate(input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    let input = pad_input(input, 96);
+
+    let p = read_g1_substrate(&input[0..64])?;
+    let fr = substrate_bn::Fr::from_slice(&input[64..96]).map_err(|_| PrecompileError::NotBelowModulus("Invalid field element"))?;
+
+    Ok(encode_g1_substrate(p * fr))
+}
+
+pub fn run_bn_pair_substrate(input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    use substrate_bn::{Gt, pairing_batch};
+
+    if input.len() % 192 != 0 {
+        return Err(PrecompileError::InvalidPairingLength("Invalid input length, must be multiple of 192 (3 * (32*2))"));
+    }
+
+    let mut pairs = vec![];
+    for chunk in input.chunks(192) {
+        let a = read_g1_substrate(&chunk[0..64])?;
+        let b = read_g2_substrate(&chunk[64..192])?;
+        pairs.push((a, b));
+    }
+
+    let mut output = vec![0u8; 32];
+    if pairs.is_empty() || pairing_batch(&pairs) == Gt::one() {
+        output[31] = 1;
+    }
+
+    Ok(output)
+}
+
+// Big endian encoding, not reduced
+fn read_ark_bigint(reader: &[u8]) -> ark_ff::BigInt<4> {
+    let mut limbs = [0u64; 4];
+    for (i, limb) in limbs.iter_mut().rev().enumerate() {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&reader[i*8..(i+1)*8]);
+        *limb = u64::from_be_bytes(word);
+    }
+
+    ark_ff::BigInt::new(limbs)
+}
+
+fn read_ark_fq(reader: &[u8]) -> Option<ark_bn254::Fq> {
+    use ark_ff::PrimeField;
+
+    ark_bn254::Fq::from_bigint(read_ark_bigint(reader))
+}
+
+fn read_g1_arkworks(reader: &[u8]) -> Result<ark_bn254::G1Affine, PrecompileError> {
+    use ark_ec::AffineRepr;
+    use ark_ff::Zero;
+
+    let x = read_ark_fq(&reader[0..32]).ok_or(PrecompileError::NotBelowModulus("Invalid point x coordinate"))?;
+    let y = read_ark_fq(&reader[32..64]).ok_or(PrecompileError::NotBelowModulus("Invalid point y coordinate"))?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(ark_bn254::G1Affine::zero());
+    }
+
+    // G1 has a cofactor of 1, so any point on curve is in the subgroup
+    let point = ark_bn254::G1Affine::new_unchecked(x, y);
+    if !point.is_on_curve() {
+        return Err(PrecompileError::NotOnCurve("Invalid curve point"));
+    }
+
+    Ok(point)
+}
+
+fn read_g2_arkworks(reader: &[u8]) -> Result<ark_bn254::G2Affine, PrecompileError> {
+    use ark_ec::AffineRepr;
+    use ark_ff::Zero;
+
+    let x_imaginary = read_ark_fq(&reader[0..32]).ok_or(PrecompileError::NotBelowModulus("Invalid b argument imaginary coeff x coordinate"))?;
+    let x_real = read_ark_fq(&reader[32..64]).ok_or(PrecompileError::NotBelowModulus("Invalid b argument real coeff x coordinate"))?;
+    let y_imaginary = read_ark_fq(&reader[64..96]).ok_or(PrecompileError::NotBelowModulus("Invalid b argument imaginary coeff y coordinate"))?;
+    let y_real = read_ark_fq(&reader[96..128]).ok_or(PrecompileError::NotBelowModulus("Invalid b argument real coeff y coordinate"))?;
+
+    let x = ark_bn254::Fq2::new(x_real, x_imaginary);
+    let y = ark_bn254::Fq2::new(y_real, y_imaginary);
+    if x.is_zero() && y.is_zero() {
+        return Ok(ark_bn254::G2Affine::zero());
+    }
+
+    let point = ark_bn254::G2Affine::new_unchecked(x, y);
+    if !point.is_on_curve() {
+        return Err(PrecompileError::NotOnCurve("Invalid b argument - not on curve"));
+    }
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(PrecompileError::NotInSubgroup("Invalid b argument - not in subgroup"));
+    }
+
+    Ok(point)
+}
+
+fn encode_g1_arkworks(point: ark_bn254::G1Projective) -> Vec<u8> {
+    use ark_ec::{AffineRepr, CurveGroup};
+    use ark_ff::{BigInteger, PrimeField};
+
+    let affine = point.into_affine();
+    let mut output = vec![0u8; 64];
+    if let Some((x, y)) = affine.xy() {
+        output[0..32].copy_from_slice(&x.into_bigint().to_bytes_be());
+        output[32..64].copy_from_slice(&y.into_bigint().to_bytes_be());
+    }
+
+    output
+}
+
+pub fn run_bn_add_arkworks(input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    let input = pad_input(input, 128);
+
+    let p1 = read_g1_arkworks(&input[0..64])?;
+    let p2 = read_g1_arkworks(&input[64..128])?;
+
+    Ok(encode_g1_arkworks(p1 + p2))
+}
+
+pub fn run_bn_mul_arkworks(input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    use ark_ec::AffineRepr;
+
+    let input = pad_input(input, 96);
+    let p = read_g1_arkworks(&input[0..64])?;
+    // the precompile accepts any 256-bit scalar, so it is not reduced into the scalar field
+    let scalar = read_ark_bigint(&input[64..96]);
+
+    Ok(encode_g1_arkworks(p.mul_bigint(scalar)))
+}
+
+pub fn run_bn_pair_arkworks(input: &[u8]) -> Result<Vec<u8>, PrecompileError> {
+    use ark_ec::pairing::Pairing;
+    use ark_ff::One;
+
+    if input.len() % 192 != 0 {
+        return Err(PrecompileError::InvalidPairingLength("Invalid input length, must be multiple of 192 (3 * (32*2))"));
+    }
+
+    let mut g1_points = vec![];
+    let mut g2_points = vec![];
+    for chunk in input.chunks(192) {
+        g1_points.push(read_g1_arkworks(&chunk[0..64])?);
+        g2_points.push(read_g2_arkworks(&chunk[64..192])?);
+    }
+
+    let mut output = vec![0u8; 32];
+    if ark_bn254::Bn254::multi_pairing(g1_points, g2_points).0.is_one() {
+        output[31] = 1;
+    }
+
+    Ok(output)
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BackendPoint {
+    #[serde(default)]
+    pub family: String,
+    pub scalar: u64,
+    pub num_vectors: usize,
+    // mean of vector means per backend, in the order of `BackendComparison::backends`
+    pub mean_ns: Vec<u128>
+}
+
+// Outcome of a backend that differs from the output or the error class of the vector
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BackendMismatch {
+    #[serde(default)]
+    pub family: String,
+    pub scalar: u64,
+    pub vector_index: usize,
+    pub backend: String,
+    pub input: String,
+    pub expected: String,
+    pub actual: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BackendComparison {
+    pub precompile: String,
+    pub backends: Vec<String>,
+    pub points: Vec<BackendPoint>,
+    pub mismatches: Vec<BackendMismatch>
+}
+
+// Runs every vector through every backend of the precompile. Outputs must match the vector and
+// invalid vectors must be rejected with the same class of error, see `fuzzing::error_class`.
+// Vectors with a mismatch are not measured. Invalid vectors are indexed after the valid ones
+pub fn compare_backends(precompile: &str, set: &VectorSet, invalid_set: &InvalidVectorSet, num_tries: usize) -> Result<BackendComparison, &'static str> {
+    let backends = backends(precompile)?;
+
+    let mut expectations: BTreeMap<(String, u64), Vec<(&[u8], Outcome)>> = BTreeMap::new();
+    for (key, vectors) in set.iter() {
+        let expected = vectors.iter().map(|(input, output)| (&input[..], Outcome::Output(output.clone())));
+        expectations.entry(key.clone()).or_default().extend(expected);
+    }
+    for (key, vectors) in invalid_set.iter() {
+        let expected = vectors.iter().map(|(input, error)| (&input[..], Outcome::Error(*error)));
+        expectations.entry(key.clone()).or_default().extend(expected);
+    }
+
+    let mut points = vec![];
+    let mut mismatches = vec![];
+    for ((family, scalar), vectors) in expectations.iter() {
+        let mut totals = vec![0u128; backends.len()];
+        let mut num_measured = 0;
+        for (index, (input, expected)) in vectors.iter().enumerate() {
+            let mut agree = true;
+            for backend in backends.iter() {
+                let actual = fuzzing::run_backend(backend, input);
+                if !actual.agrees_with(expected) {
+                    agree = false;
+                    mismatches.push(BackendMismatch {
+                        family: family.clone(),
+                        scalar: *scalar,
+                        vector_index: index,
+                        backend: backend.name.to_string(),
+                        input: hex::encode(input),
+                        expected: expected.describe(),
+                        actual: actual.describe()
+                    });
+                }
+            }
+            if !agree {
+                continue;
+            }
+
+            for (total, backend) in totals.iter_mut().zip(backends.iter()) {
+                let runnable = || (backend.run)(input);
+                let checker = |r: Result<Vec<u8>, PrecompileError>| Outcome::from_result(r).agrees_with(expected);
+                let samples = measurements::measure_samples_with_validity(&runnable, &checker, num_tries, Some(MEASUREMENT_BUDGET_NS));
+                *total += TimingStats::from_samples(&samples).mean_ns;
+            }
+            num_measured += 1;
+        }
+
+        if num_measured > 0 {
+            points.push(BackendPoint {
+                family: family.clone(),
+                scalar: *scalar,
+                num_vectors: num_measured,
+                mean_ns: totals.into_iter().map(|t| t / (num_measured as u128)).collect()
+            });
+        }
+    }
+
+    Ok(BackendComparison {
+        precompile: precompile.to_string(),
+        backends: backends.iter().map(|b| b.name.to_string()).collect(),
+        points,
+        mismatches
+    })
+}
+
+// Side by side table of mean times, e.g. `param | bn ns | substrate-bn ns | arkworks ns`
+pub fn render_comparison(comparison: &BackendComparison) -> String {
+    let mut out = String::new();
+
+    let mut header = vec![String::from("param")];
+    header.extend(comparison.backends.iter().map(|b| format!("{} ns", b)));
+    out.push_str(&format!("| {} |\n", header.join(" | ")));
+    out.push_str(&format!("|{}\n", "---:|".repeat(header.len())));
+
+    for point in comparison.points.iter() {
+        let mut row = vec![if point.family.is_empty() {
+            point.scalar.to_string()
+        } else {
+            format!("{} ({})", point.scalar, point.family)
+        }];
+        row.extend(point.mean_ns.iter().map(|ns| ns.to_string()));
+        out.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+
+    out
+}
+
+pub fn write_backend_comparisons(path: &str, comparisons: &[BackendComparison]) -> std::io::Result<()> {
+    helpers::write_atomically(path, |file| {
+        serde_json::to_writer_pretty(file, comparisons)?;
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::determinism::{self, PRECOMPILE_NAMES};
+    use crate::generator::RunParameters;
+
+    #[test]
+    #[ignore]
+    fn compare_precompile_backends() {
+        let vectors_path = std::env::var("BENCH_VECTORS_PATH").unwrap_or_else(|_| String::from("./vectors"));
+        let output = std::env::var(BACKENDS_OUTPUT_ENV)
+            .unwrap_or_else(|_| format!("{}/{}", vectors_path, BACKENDS_FILE_NAME));
+        let num_tries = std::env::var(BACKEND_TRIES_ENV).ok()
+            .map(|t| t.parse::<usize>().expect("number of tries must be an integer"))
+            .unwrap_or(1000);
+        let precompiles: Vec<String> = std::env::var(BACKEND_PRECOMPILES_ENV).ok()
+            .map(|p| p.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+            .unwrap_or_else(|| PRECOMPILE_NAMES.iter().map(|p| p.to_string()).collect());
+        // same vectors as the `generate_*` tests
+        let params = RunParameters::from_env(10, 0);
+
+        let mut comparisons = vec![];
+        for precompile in precompiles.iter() {
+            let (set, invalid_set) = determinism::generate_vector_sets(precompile, &params).unwrap();
+            let comparison = compare_backends(precompile, &set, &invalid_set, num_tries).unwrap();
+
+            println!("{}:\n{}", precompile, render_comparison(&comparison));
+            for m in comparison.mismatches.iter() {
+                println!("{} for {} (vector {}): {} returned {}, expected {}", precompile, m.scalar, m.vector_index, m.backend, m.actual, m.expected);
+            }
+            comparisons.push(comparison);
+        }
+
+        write_backend_comparisons(&output, &comparisons).unwrap();
+
+        let num_mismatches: usize = comparisons.iter().map(|c| c.mismatches.len()).sum();
+        assert!(num_mismatches == 0, "{} backend mismatch(es), see {}", num_mismatches, output);
+    }
+}
@@ -0,0 +1,201 @@
+use serde::{Serialize, Deserialize};
+
+use super::generator::MGAS_PER_SECOND;
+use super::measurements::{self, TimingStats};
+use super::{pricers, runners};
+
+pub const GAS_PER_SECOND_ENV: &str = "BENCH_GAS_PER_SECOND";
+pub const GAS_ANCHOR_ENV: &str = "BENCH_GAS_ANCHOR";
+pub const ANCHOR_SAMPLES_ENV: &str = "BENCH_ANCHOR_SAMPLES";
+
+pub const DEFAULT_ANCHOR: &str = "ecrecover";
+pub const ANCHORS: [&str; 1] = ["ecrecover"];
+pub const DEFAULT_ANCHOR_SAMPLES: usize = 10000;
+
+// Valid signatures from the ECRECOVER benchmark in `lib.rs`
+const ECRECOVER_ANCHOR_INPUTS: [&str; 2] = [
+    "38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e000000000000000000000000000000000000000000000000000000000000001b38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e789d1dd423d25f0772d2748d60f7e4b81bb14d086eba8e8e8efb6dcff8a4ae02",
+    "47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b650acf9d3f5f0a2c799776a1254355d5f4061762a237396a99a0e0e3fc2bcd6729514a0dacb2e623ac4abd157cb18163ff942280db4d5caad66ddf941ba12e03"
+];
+
+// Measurement of a reference precompile that the gas rate was derived from
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Anchor {
+    pub precompile: String,
+    pub scheduled_gas: u64,
+    pub timing: TimingStats
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GasRate {
+    pub gas_per_second: u128,
+    pub anchor: Option<Anchor>
+}
+
+impl GasRate {
+    pub fn fixed(gas_per_second: u128) -> Self {
+        Self {
+            gas_per_second,
+            anchor: None
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.anchor {
+            Some(anchor) => format!("{} gas/second, anchored to {} ({} gas in {} ns)",
+                self.gas_per_second, anchor.precompile, anchor.scheduled_gas, anchor.timing.mean_ns),
+            None => format!("{} gas/second", self.gas_per_second)
+        }
+    }
+}
+
+impl Default for GasRate {
+    fn default() -> Self {
+        Self::fixed(MGAS_PER_SECOND)
+    }
+}
+
+// How the gas rate of a run is chosen, read from the environment and validated before anything
+// is measured
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GasRateSource {
+    Default,
+    Fixed(u128),
+    Anchor { precompile: String, num_samples: usize }
+}
+
+impl GasRateSource {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(
+            std::env::var(GAS_PER_SECOND_ENV).ok().as_deref(),
+            std::env::var(GAS_ANCHOR_ENV).ok().as_deref(),
+            std::env::var(ANCHOR_SAMPLES_ENV).ok().as_deref()
+        )
+    }
+
+    pub fn parse(fixed: Option<&str>, anchor: Option<&str>, num_samples: Option<&str>) -> Result<Self, String> {
+        match (fixed, anchor) {
+            (Some(_), Some(_)) => {
+                Err(format!("only one of {} and {} can be set", GAS_PER_SECOND_ENV, GAS_ANCHOR_ENV))
+            },
+            (Some(fixed), None) => {
+                let gas_per_second = fixed.trim().replace('_', "").parse::<u128>()
+                    .map_err(|_| format!("{} must be an integer, got `{}`", GAS_PER_SECOND_ENV, fixed))?;
+                if gas_per_second == 0 {
+                    return Err(format!("{} must be positive", GAS_PER_SECOND_ENV));
+                }
+
+                Ok(GasRateSource::Fixed(gas_per_second))
+            },
+            (None, Some(anchor)) => {
+                let precompile = if anchor.trim().is_empty() { DEFAULT_ANCHOR } else { anchor.trim() };
+                if !ANCHORS.contains(&precompile) {
+                    return Err(format!("{} must be one of {:?}, got `{}`", GAS_ANCHOR_ENV, ANCHORS, precompile));
+                }
+                let num_samples = match num_samples {
+                    Some(n) => n.trim().parse::<usize>()
+                        .map_err(|_| format!("{} must be an integer, got `{}`", ANCHOR_SAMPLES_ENV, n))?,
+                    None => DEFAULT_ANCHOR_SAMPLES
+                };
+                if num_samples == 0 {
+                    return Err(format!("{} must be positive", ANCHOR_SAMPLES_ENV));
+                }
+
+                Ok(GasRateSource::Anchor { precompile: String::from(precompile), num_samples })
+            },
+            (None, None) => Ok(GasRateSource::Default)
+        }
+    }
+
+    pub fn gas_rate(&self) -> Result<GasRate, String> {
+        match self {
+            GasRateSource::Default => Ok(GasRate::default()),
+            GasRateSource::Fixed(gas_per_second) => Ok(GasRate::fixed(*gas_per_second)),
+            GasRateSource::Anchor { precompile, num_samples } => {
+                anchored_gas_rate(precompile, *num_samples).map_err(|e| format!("{}: {}", GAS_ANCHOR_ENV, e))
+            }
+        }
+    }
+}
+
+// `BENCH_GAS_PER_SECOND` sets the rate explicitly, `BENCH_GAS_ANCHOR` (e.g. `ecrecover`) derives it
+// from a running time of the reference precompile and its scheduled price. Settings are validated
+// and the anchor is measured only once per process, so every precompile of the run is normalized
+// to the same measurement. Benchmarks call this before measuring, so bad settings fail early
+pub fn gas_rate_from_env() -> GasRate {
+    static GAS_RATE: std::sync::OnceLock<Result<GasRate, String>> = std::sync::OnceLock::new();
+
+    let rate = GAS_RATE.get_or_init(|| {
+        let rate = GasRateSource::from_env()?.gas_rate()?;
+        println!("Using {}", rate.describe());
+
+        Ok(rate)
+    });
+
+    match rate {
+        Ok(rate) => rate.clone(),
+        Err(e) => panic!("invalid gas rate settings: {}", e)
+    }
+}
+
+pub fn anchored_gas_rate(anchor: &str, num_samples: usize) -> Result<GasRate, &'static str> {
+    if num_samples == 0 {
+        return Err("anchor requires at least one sample");
+    }
+
+    let (scheduled_gas, samples) = match anchor {
+        "ecrecover" => {
+            let inputs: Vec<Vec<u8>> = ECRECOVER_ANCHOR_INPUTS.iter()
+                .map(|i| hex::decode(i).expect("anchor inputs are valid hex"))
+                .collect();
+
+            let mut samples = Vec::with_capacity(num_samples);
+            for input in inputs.iter() {
+                let runnable = || runners::run_ecrecover(input);
+                let checker = |output: [u8; 32]| output != [0u8; 32];
+                samples.extend(measurements::measure_samples_with_validity(&runnable, &checker, num_samples / inputs.len() + 1, None));
+            }
+
+            (pricers::ecrecover_pricer().price(0), samples)
+        },
+        _ => {
+            return Err("unknown anchor precompile");
+        }
+    };
+
+    let timing = TimingStats::from_samples(&samples);
+    if timing.mean_ns == 0 {
+        return Err("anchor running time is below timer resolution");
+    }
+
+    Ok(GasRate {
+        gas_per_second: (scheduled_gas as u128) * 1_000_000_000 / timing.mean_ns,
+        anchor: Some(Anchor {
+            precompile: anchor.to_string(),
+            scheduled_gas,
+            timing
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_gas_rate_source_parse() {
+        assert_eq!(GasRateSource::parse(None, None, None), Ok(GasRateSource::Default));
+        assert_eq!(GasRateSource::parse(Some("35_000_000"), None, None), Ok(GasRateSource::Fixed(35_000_000)));
+        assert_eq!(GasRateSource::parse(None, Some(""), None),
+            Ok(GasRateSource::Anchor { precompile: String::from(DEFAULT_ANCHOR), num_samples: DEFAULT_ANCHOR_SAMPLES }));
+        assert_eq!(GasRateSource::parse(None, Some("ecrecover"), Some("100")),
+            Ok(GasRateSource::Anchor { precompile: String::from("ecrecover"), num_samples: 100 }));
+
+        assert!(GasRateSource::parse(Some("35000000"), Some("ecrecover"), None).is_err());
+        assert!(GasRateSource::parse(Some("35M"), None, None).is_err());
+        assert!(GasRateSource::parse(Some("0"), None, None).is_err());
+        assert!(GasRateSource::parse(None, Some("sha256"), None).is_err());
+        assert!(GasRateSource::parse(None, Some("ecrecover"), Some("0")).is_err());
+        assert!(GasRateSource::parse(None, Some("ecrecover"), Some("many")).is_err());
+    }
+}
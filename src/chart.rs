@@ -0,0 +1,116 @@
+use super::report::ScalarSummary;
+
+const WIDTH: f64 = 720f64;
+const HEIGHT: f64 = 420f64;
+const MARGIN_LEFT: f64 = 80f64;
+const MARGIN_RIGHT: f64 = 150f64;
+const MARGIN_TOP: f64 = 40f64;
+const MARGIN_BOTTOM: f64 = 50f64;
+const NUM_TICKS: usize = 5;
+
+const SCHEDULE_COLORS: [&str; 6] = ["#cf222e", "#1a7f37", "#8250df", "#bf8700", "#0969da", "#57606a"];
+
+// Time that a schedule allows for a given gas at `gas_per_second` rate
+pub fn gas_to_ns(gas: u64, gas_per_second: u128) -> f64 {
+    (gas as f64) * 1_000_000_000f64 / (gas_per_second as f64)
+}
+
+// Plot of the measured mean time (error bars are one pooled standard deviation) against the
+// scalar parameter, overlaid with every schedule converted into time. Returns `None` if there
+// is nothing to draw a line through, e.g. for precompiles measured for a single parameter
+pub fn render_svg(title: &str, summaries: &[ScalarSummary], schedules: &[String], gas_per_second: u128) -> Option<String> {
+    if summaries.len() < 2 {
+        return None;
+    }
+
+    let min_x = summaries.iter().map(|s| s.scalar).min()? as f64;
+    let max_x = summaries.iter().map(|s| s.scalar).max()? as f64;
+    if max_x <= min_x {
+        return None;
+    }
+
+    let mut max_y = 0f64;
+    for s in summaries.iter() {
+        max_y = max_y.max((s.mean_ns as f64) + s.stddev_ns);
+        for g in s.schedule_gas.values() {
+            max_y = max_y.max(gas_to_ns(*g, gas_per_second));
+        }
+    }
+    if max_y <= 0f64 {
+        max_y = 1f64;
+    }
+    let max_y = max_y * 1.05f64;
+
+    let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let x = |v: f64| MARGIN_LEFT + (v - min_x) / (max_x - min_x) * plot_width;
+    let y = |v: f64| MARGIN_TOP + plot_height - v / max_y * plot_height;
+
+    let mut out = String::new();
+    out.push_str(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"sans-serif\" font-size=\"12\">\n", WIDTH, HEIGHT, WIDTH, HEIGHT));
+    out.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", WIDTH, HEIGHT));
+    out.push_str(&format!("<text x=\"{}\" y=\"24\" text-anchor=\"middle\" font-size=\"16\">{}</text>\n", MARGIN_LEFT + plot_width / 2f64, escape_xml(title)));
+
+    // axes with ticks
+    out.push_str(&format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/>\n", MARGIN_LEFT, MARGIN_TOP + plot_height, MARGIN_LEFT + plot_width, MARGIN_TOP + plot_height));
+    out.push_str(&format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/>\n", MARGIN_LEFT, MARGIN_TOP, MARGIN_LEFT, MARGIN_TOP + plot_height));
+    for i in 0..=NUM_TICKS {
+        let xv = min_x + (max_x - min_x) * (i as f64) / (NUM_TICKS as f64);
+        out.push_str(&format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/>\n", x(xv), MARGIN_TOP + plot_height, x(xv), MARGIN_TOP + plot_height + 5f64));
+        out.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{:.0}</text>\n", x(xv), MARGIN_TOP + plot_height + 18f64, xv));
+
+        let yv = max_y * (i as f64) / (NUM_TICKS as f64);
+        out.push_str(&format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#ddd\"/>\n", MARGIN_LEFT, y(yv), MARGIN_LEFT + plot_width, y(yv)));
+        out.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{:.0}</text>\n", MARGIN_LEFT - 6f64, y(yv) + 4f64, yv));
+    }
+    out.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">parameter</text>\n", MARGIN_LEFT + plot_width / 2f64, HEIGHT - 10f64));
+    out.push_str(&format!("<text x=\"16\" y=\"{:.1}\" text-anchor=\"middle\" transform=\"rotate(-90 16 {:.1})\">ns</text>\n", MARGIN_TOP + plot_height / 2f64, MARGIN_TOP + plot_height / 2f64));
+
+    // schedules
+    let mut legend = vec![];
+    for (i, schedule) in schedules.iter().enumerate() {
+        let color = SCHEDULE_COLORS[i % SCHEDULE_COLORS.len()];
+        let points: Vec<String> = summaries.iter()
+            .filter_map(|s| s.schedule_gas.get(schedule).map(|g| (s.scalar, *g)))
+            .map(|(scalar, g)| format!("{:.1},{:.1}", x(scalar as f64), y(gas_to_ns(g, gas_per_second))))
+            .collect();
+        out.push_str(&format!("<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\" stroke-dasharray=\"6 3\"/>\n", points.join(" "), color));
+        legend.push((format!("{} schedule", schedule), color, true));
+    }
+
+    // measurements
+    let points: Vec<String> = summaries.iter()
+        .map(|s| format!("{:.1},{:.1}", x(s.scalar as f64), y(s.mean_ns as f64)))
+        .collect();
+    out.push_str(&format!("<polyline points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>\n", points.join(" ")));
+    for s in summaries.iter() {
+        let cx = x(s.scalar as f64);
+        let mean = s.mean_ns as f64;
+        let low = (mean - s.stddev_ns).max(0f64);
+        let high = mean + s.stddev_ns;
+        out.push_str(&format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/>\n", cx, y(low), cx, y(high)));
+        out.push_str(&format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/>\n", cx - 3f64, y(low), cx + 3f64, y(low)));
+        out.push_str(&format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/>\n", cx - 3f64, y(high), cx + 3f64, y(high)));
+        out.push_str(&format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"black\"><title>{}: {} ns</title></circle>\n", cx, y(mean), s.scalar, s.mean_ns));
+    }
+    legend.push((String::from("measured"), "black", false));
+
+    for (i, (label, color, dashed)) in legend.into_iter().enumerate() {
+        let ly = MARGIN_TOP + 10f64 + (i as f64) * 18f64;
+        let lx = MARGIN_LEFT + plot_width + 12f64;
+        out.push_str(&format!("<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"2\"{}/>\n",
+            lx, ly, lx + 20f64, ly, color, if dashed { " stroke-dasharray=\"6 3\"" } else { "" }));
+        out.push_str(&format!("<text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n", lx + 26f64, ly + 4f64, escape_xml(&label)));
+    }
+
+    out.push_str("</svg>\n");
+
+    Some(out)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
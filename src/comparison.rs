@@ -0,0 +1,550 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use super::helpers;
+use super::results::{self, MeasurementResults, TimingRecord};
+
+pub const BASELINE_ENV: &str = "BENCH_BASELINE";
+pub const CANDIDATE_ENV: &str = "BENCH_CANDIDATE";
+pub const REGRESSION_THRESHOLD_ENV: &str = "BENCH_REGRESSION_THRESHOLD";
+pub const SIGNIFICANCE_ENV: &str = "BENCH_SIGNIFICANCE";
+pub const COMPARISON_OUTPUT_ENV: &str = "BENCH_COMPARISON_OUTPUT";
+
+// relative slowdown in percent that is reported as a regression
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 5f64;
+// largest p-value that is considered significant
+pub const DEFAULT_SIGNIFICANCE: f64 = 0.01f64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Verdict {
+    Regression,
+    Speedup,
+    Unchanged
+}
+
+// How a point is compared. Vectors of the same parameter differ in cost (e.g. by the Hamming
+// weight of a scalar), so whenever possible every vector is compared with itself
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ComparisonMethod {
+    // paired t-test over relative changes of vectors present in both runs
+    PairedVectors,
+    // Welch's t-test over calls of the only vector present in both runs
+    SingleVectorCalls,
+    // Welch's t-test over vector means, if runs have no vector in common
+    VectorMeans
+}
+
+// Mean and standard deviation of `observations`, which are vector means or calls of one vector
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct PooledTiming {
+    pub observations: usize,
+    pub mean_ns: f64,
+    pub stddev_ns: f64
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PointComparison {
+    pub precompile: String,
+    #[serde(default)]
+    pub family: String,
+    pub scalar: u64,
+    pub method: ComparisonMethod,
+    pub paired_vectors: usize,
+    pub baseline: PooledTiming,
+    pub candidate: PooledTiming,
+    pub change_percent: f64,
+    pub t_statistic: f64,
+    pub p_value: f64,
+    pub confidence_percent: f64,
+    pub verdict: Verdict
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Comparison {
+    pub regression_threshold_percent: f64,
+    pub significance: f64,
+    pub points: Vec<PointComparison>,
+    // (precompile, family, scalar) present only in one of the result sets
+    pub only_in_baseline: Vec<(String, String, u64)>,
+    pub only_in_candidate: Vec<(String, String, u64)>
+}
+
+impl Comparison {
+    pub fn regressions(&self) -> Vec<&PointComparison> {
+        self.points.iter().filter(|p| p.verdict == Verdict::Regression).collect()
+    }
+}
+
+// `path` is either a single results file or directory, or a tree of precompile results
+pub fn load_results(path: &str) -> std::io::Result<Vec<MeasurementResults>> {
+    let as_path = std::path::Path::new(path);
+    if as_path.is_file() || as_path.join(results::TIMINGS_JSON_FILE_NAME).exists() {
+        return Ok(vec![results::read_results(path)?]);
+    }
+
+    results::find_results(path)
+}
+
+// Vector means as observations
+pub fn pool_records(records: &[&TimingRecord]) -> PooledTiming {
+    let observations = records.len();
+    if observations == 0 {
+        return PooledTiming { observations: 0, mean_ns: 0f64, stddev_ns: 0f64 };
+    }
+
+    let mean_ns = records.iter().map(|r| r.mean_ns as f64).sum::<f64>() / (observations as f64);
+    if observations == 1 {
+        return PooledTiming { observations, mean_ns, stddev_ns: 0f64 };
+    }
+
+    let sum_of_squares: f64 = records.iter().map(|r| {
+        let d = (r.mean_ns as f64) - mean_ns;
+
+        d * d
+    }).sum();
+
+    PooledTiming {
+        observations,
+        mean_ns,
+        stddev_ns: (sum_of_squares / ((observations - 1) as f64)).sqrt()
+    }
+}
+
+// Calls of a single vector as observations
+pub fn vector_calls(record: &TimingRecord) -> PooledTiming {
+    PooledTiming {
+        observations: record.samples,
+        mean_ns: record.mean_ns as f64,
+        stddev_ns: record.stddev_ns
+    }
+}
+
+// Vectors are matched by test name, or by seed in results written before names were recorded
+fn vector_key(record: &TimingRecord) -> &str {
+    if record.name.is_empty() {
+        &record.seed
+    } else {
+        &record.name
+    }
+}
+
+// (baseline, candidate) records of the same vector
+pub fn pair_records<'a>(baseline: &[&'a TimingRecord], candidate: &[&'a TimingRecord]) -> Vec<(&'a TimingRecord, &'a TimingRecord)> {
+    let candidate: BTreeMap<&str, &TimingRecord> = candidate.iter()
+        .filter(|r| !vector_key(r).is_empty())
+        .map(|r| (vector_key(r), *r))
+        .collect();
+
+    baseline.iter()
+        .filter_map(|b| candidate.get(vector_key(b)).map(|c| (*b, *c)))
+        .collect()
+}
+
+fn compare_point(key: &(String, String, u64), baseline: &[&TimingRecord], candidate: &[&TimingRecord], regression_threshold_percent: f64, significance: f64) -> PointComparison {
+    let pairs = pair_records(baseline, candidate);
+
+    let (method, b, c, change_percent, t_statistic, p_value) = match pairs.len() {
+        0 => {
+            let b = pool_records(baseline);
+            let c = pool_records(candidate);
+            let (t, p) = welch_t_test(&b, &c);
+
+            (ComparisonMethod::VectorMeans, b, c, relative_change_percent(b.mean_ns, c.mean_ns), t, p)
+        },
+        1 => {
+            let b = vector_calls(pairs[0].0);
+            let c = vector_calls(pairs[0].1);
+            let (t, p) = welch_t_test(&b, &c);
+
+            (ComparisonMethod::SingleVectorCalls, b, c, relative_change_percent(b.mean_ns, c.mean_ns), t, p)
+        },
+        _ => {
+            let changes: Vec<f64> = pairs.iter()
+                .map(|(b, c)| relative_change_percent(b.mean_ns as f64, c.mean_ns as f64))
+                .collect();
+            let (mean_change, t, p) = paired_t_test(&changes);
+            let b: Vec<&TimingRecord> = pairs.iter().map(|(b, _)| *b).collect();
+            let c: Vec<&TimingRecord> = pairs.iter().map(|(_, c)| *c).collect();
+
+            (ComparisonMethod::PairedVectors, pool_records(&b), pool_records(&c), mean_change, t, p)
+        }
+    };
+
+    let verdict = if p_value > significance || change_percent.abs() < regression_threshold_percent {
+        Verdict::Unchanged
+    } else if change_percent > 0f64 {
+        Verdict::Regression
+    } else {
+        Verdict::Speedup
+    };
+
+    PointComparison {
+        precompile: key.0.clone(),
+        family: key.1.clone(),
+        scalar: key.2,
+        method,
+        paired_vectors: pairs.len(),
+        baseline: b,
+        candidate: c,
+        change_percent,
+        t_statistic,
+        p_value,
+        confidence_percent: (1f64 - p_value) * 100f64,
+        verdict
+    }
+}
+
+fn relative_change_percent(baseline_ns: f64, candidate_ns: f64) -> f64 {
+    if baseline_ns > 0f64 {
+        (candidate_ns - baseline_ns) / baseline_ns * 100f64
+    } else {
+        0f64
+    }
+}
+
+pub fn compare(baseline: &[MeasurementResults], candidate: &[MeasurementResults], regression_threshold_percent: f64, significance: f64) -> Comparison {
+    let baseline = group_by_point(baseline);
+    let candidate = group_by_point(candidate);
+
+    let mut points = vec![];
+    let mut only_in_baseline = vec![];
+    for (key, baseline_records) in baseline.iter() {
+        let candidate_records = match candidate.get(key) {
+            Some(records) => records,
+            None => {
+                only_in_baseline.push(key.clone());
+                continue;
+            }
+        };
+
+        points.push(compare_point(key, baseline_records, candidate_records, regression_threshold_percent, significance));
+    }
+
+    let only_in_candidate = candidate.keys()
+        .filter(|k| !baseline.contains_key(*k))
+        .cloned()
+        .collect();
+
+    Comparison {
+        regression_threshold_percent,
+        significance,
+        points,
+        only_in_baseline,
+        only_in_candidate
+    }
+}
+
+pub fn print_comparison(comparison: &Comparison) {
+    use colored::*;
+
+    for p in comparison.points.iter() {
+        let verdict = match p.verdict {
+            Verdict::Regression => "regression".red(),
+            Verdict::Speedup => "speedup".green(),
+            Verdict::Unchanged => "unchanged".normal()
+        };
+        println!("{} for {}: {:.0} ns -> {:.0} ns ({:+.2}%, {:?}), p = {:.3e}, confidence {:.2}%: {}",
+            p.precompile, describe_param(&p.family, p.scalar), p.baseline.mean_ns, p.candidate.mean_ns, p.change_percent, p.method, p.p_value, p.confidence_percent, verdict);
+    }
+
+    for (precompile, family, scalar) in comparison.only_in_baseline.iter() {
+        println!("{} for {}: missing in candidate", precompile, describe_param(family, *scalar));
+    }
+    for (precompile, family, scalar) in comparison.only_in_candidate.iter() {
+        println!("{} for {}: missing in baseline", precompile, describe_param(family, *scalar));
+    }
+}
+
+fn describe_param(family: &str, scalar: u64) -> String {
+    if family.is_empty() {
+        scalar.to_string()
+    } else {
+        format!("{} ({})", scalar, family)
+    }
+}
+
+pub fn write_comparison(path: &str, comparison: &Comparison) -> std::io::Result<()> {
+    helpers::write_atomically(path, |file| {
+        serde_json::to_writer_pretty(file, comparison)?;
+
+        Ok(())
+    })
+}
+
+// Returns mean difference, t statistic and two-sided p-value of a paired t-test, `differences`
+// are the differences within pairs
+pub fn paired_t_test(differences: &[f64]) -> (f64, f64, f64) {
+    let n = differences.len();
+    if n == 0 {
+        return (0f64, 0f64, 1f64);
+    }
+
+    let mean = differences.iter().sum::<f64>() / (n as f64);
+    if n == 1 {
+        return (mean, 0f64, 1f64);
+    }
+
+    let variance = differences.iter().map(|d| (d - mean) * (d - mean)).sum::<f64>() / ((n - 1) as f64);
+    if variance == 0f64 {
+        // every pair changed the same way, so any difference is certain
+        return if mean == 0f64 { (mean, 0f64, 1f64) } else { (mean, mean.signum() * f64::INFINITY, 0f64) };
+    }
+
+    let t = mean / (variance / (n as f64)).sqrt();
+
+    (mean, t, student_t_two_sided_p_value(t, (n - 1) as f64))
+}
+
+// Returns t statistic and two-sided p-value of Welch's unequal variances t-test
+pub fn welch_t_test(a: &PooledTiming, b: &PooledTiming) -> (f64, f64) {
+    if a.observations < 2 || b.observations < 2 {
+        return (0f64, 1f64);
+    }
+
+    let va = a.stddev_ns * a.stddev_ns / (a.observations as f64);
+    let vb = b.stddev_ns * b.stddev_ns / (b.observations as f64);
+    let diff = b.mean_ns - a.mean_ns;
+
+    if va + vb == 0f64 {
+        // no variance at all, so any difference is certain
+        return if diff == 0f64 { (0f64, 1f64) } else { (diff.signum() * f64::INFINITY, 0f64) };
+    }
+
+    let t = diff / (va + vb).sqrt();
+    let df = (va + vb) * (va + vb) / (va * va / ((a.observations - 1) as f64) + vb * vb / ((b.observations - 1) as f64));
+
+    (t, student_t_two_sided_p_value(t, df))
+}
+
+pub fn student_t_two_sided_p_value(t: f64, df: f64) -> f64 {
+    if !t.is_finite() {
+        return 0f64;
+    }
+
+    regularized_incomplete_beta(df / (df + t * t), df / 2f64, 0.5f64).clamp(0f64, 1f64)
+}
+
+// I_x(a, b) using the continued fraction expansion
+pub fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0f64 {
+        return 0f64;
+    }
+    if x >= 1f64 {
+        return 1f64;
+    }
+
+    let ln_front = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1f64 - x).ln();
+    let front = ln_front.exp();
+
+    // continued fraction converges quickly only for x < (a + 1) / (a + b + 2)
+    if x < (a + 1f64) / (a + b + 2f64) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1f64 - front * beta_continued_fraction(1f64 - x, b, a) / b
+    }
+}
+
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 300;
+    const EPSILON: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+
+    let qab = a + b;
+    let qap = a + 1f64;
+    let qam = a - 1f64;
+
+    let mut c = 1f64;
+    let mut d = 1f64 - qab * x / qap;
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1f64 / d;
+    let mut h = d;
+
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        let m2 = 2f64 * m;
+
+        let aa = m * (b - m) * x / ((qam + m2) * (a + m2));
+        d = 1f64 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1f64 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1f64 / d;
+        h *= d * c;
+
+        let aa = -(a + m) * (qab + m) * x / ((a + m2) * (qap + m2));
+        d = 1f64 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1f64 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1f64 / d;
+        let delta = d * c;
+        h *= delta;
+
+        if (delta - 1f64).abs() < EPSILON {
+            break;
+        }
+    }
+
+    h
+}
+
+// Lanczos approximation, g = 7
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7
+    ];
+
+    if x < 0.5f64 {
+        // reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1f64 - x);
+    }
+
+    let x = x - 1f64;
+    let mut sum = COEFFICIENTS[0];
+    for (i, c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + 7.5f64;
+
+    0.5f64 * (2f64 * std::f64::consts::PI).ln() + (x + 0.5f64) * t.ln() - t + sum.ln()
+}
+
+fn group_by_point(all_results: &[MeasurementResults]) -> BTreeMap<(String, String, u64), Vec<&TimingRecord>> {
+    let mut grouped: BTreeMap<(String, String, u64), Vec<&TimingRecord>> = BTreeMap::new();
+    for results in all_results.iter() {
+        for r in results.records.iter() {
+            grouped.entry((r.precompile.clone(), r.family.clone(), r.scalar)).or_default().push(r);
+        }
+    }
+
+    grouped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_student_t_p_values() {
+        // reference values of two-sided p-values
+        let cases = [
+            (2.228f64, 10f64, 0.05f64),
+            (3.169f64, 10f64, 0.01f64),
+            (1.96f64, 1e6f64, 0.05f64),
+            (0f64, 5f64, 1f64)
+        ];
+        for (t, df, expected) in cases.iter() {
+            let p = student_t_two_sided_p_value(*t, *df);
+            assert!((p - expected).abs() < 1e-3, "p-value for t = {}, df = {} is {}, expected {}", t, df, p, expected);
+        }
+    }
+
+    fn timing_record(name: &str, mean_ns: u128) -> TimingRecord {
+        TimingRecord {
+            precompile: String::from("bnmul"),
+            family: String::new(),
+            scalar: 0,
+            name: name.to_string(),
+            label: String::new(),
+            expected_error: None,
+            seed: String::new(),
+            samples: 10000,
+            median_ns: mean_ns,
+            mean_ns,
+            stddev_ns: 1f64,
+            gas: 0,
+            schedule_gas: BTreeMap::new()
+        }
+    }
+
+    #[test]
+    fn test_pool_records() {
+        let records = [timing_record("a", 100), timing_record("b", 110), timing_record("c", 120)];
+        let refs: Vec<&TimingRecord> = records.iter().collect();
+
+        // every vector is one observation regardless of its number of samples
+        let pooled = pool_records(&refs);
+        assert_eq!(pooled.observations, 3);
+        assert!((pooled.mean_ns - 110f64).abs() < 1e-9);
+        assert!((pooled.stddev_ns - 10f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_compare_point() {
+        let key = (String::from("bnmul"), String::new(), 0u64);
+
+        // vectors of very different cost, every one is 10% slower
+        let baseline = [timing_record("a", 1000), timing_record("b", 2000), timing_record("c", 3000)];
+        let candidate = [timing_record("c", 3290), timing_record("a", 1100), timing_record("b", 2210)];
+        let b: Vec<&TimingRecord> = baseline.iter().collect();
+        let c: Vec<&TimingRecord> = candidate.iter().collect();
+        let point = compare_point(&key, &b, &c, DEFAULT_REGRESSION_THRESHOLD, DEFAULT_SIGNIFICANCE);
+        assert_eq!(point.method, ComparisonMethod::PairedVectors);
+        assert_eq!(point.paired_vectors, 3);
+        assert_eq!(point.verdict, Verdict::Regression);
+        assert!((point.change_percent - 10f64).abs() < 0.5);
+
+        // pooled as unpaired vector means the spread between vectors hides the slowdown
+        let (_, p) = welch_t_test(&pool_records(&b), &pool_records(&c));
+        assert!(p > DEFAULT_SIGNIFICANCE);
+
+        // a single vector is compared over its calls
+        let baseline = [timing_record("a", 1000)];
+        let candidate = [timing_record("a", 1100)];
+        let point = compare_point(&key, &[&baseline[0]], &[&candidate[0]], DEFAULT_REGRESSION_THRESHOLD, DEFAULT_SIGNIFICANCE);
+        assert_eq!(point.method, ComparisonMethod::SingleVectorCalls);
+        assert_eq!(point.verdict, Verdict::Regression);
+
+        // without vectors in common vector means are compared, a single one per run says nothing
+        let candidate = [timing_record("x", 1000)];
+        let point = compare_point(&key, &[&baseline[0]], &[&candidate[0]], DEFAULT_REGRESSION_THRESHOLD, DEFAULT_SIGNIFICANCE);
+        assert_eq!(point.method, ComparisonMethod::VectorMeans);
+        assert_eq!(point.verdict, Verdict::Unchanged);
+    }
+
+    #[test]
+    #[ignore]
+    fn compare_measurement_results() {
+        let baseline_path = std::env::var(BASELINE_ENV).expect("path to the baseline results must be set");
+        let candidate_path = std::env::var(CANDIDATE_ENV).unwrap_or_else(|_| String::from("./vectors"));
+        let threshold = std::env::var(REGRESSION_THRESHOLD_ENV).ok()
+            .map(|t| t.parse::<f64>().expect("threshold must be a number of percents"))
+            .unwrap_or(DEFAULT_REGRESSION_THRESHOLD);
+        let significance = std::env::var(SIGNIFICANCE_ENV).ok()
+            .map(|s| s.parse::<f64>().expect("significance must be a number"))
+            .unwrap_or(DEFAULT_SIGNIFICANCE);
+
+        let baseline = load_results(&baseline_path).unwrap();
+        let candidate = load_results(&candidate_path).unwrap();
+
+        let comparison = compare(&baseline, &candidate, threshold, significance);
+        print_comparison(&comparison);
+
+        if let Ok(output) = std::env::var(COMPARISON_OUTPUT_ENV) {
+            write_comparison(&output, &comparison).unwrap();
+        }
+
+        let regressions = comparison.regressions();
+        assert!(regressions.is_empty(), "{} point(s) regressed by more than {}%", regressions.len(), threshold);
+    }
+}
@@ -0,0 +1,139 @@
+use serde::{Serialize, Deserialize};
+
+use super::helpers;
+use super::results::{self, MeasurementResults};
+
+pub const SCHEDULES_ENV: &str = "BENCH_SCHEDULES";
+pub const SAFETY_FACTOR_ENV: &str = "BENCH_SAFETY_FACTOR";
+pub const VIOLATIONS_OUTPUT_ENV: &str = "BENCH_VIOLATIONS_OUTPUT";
+
+pub const VIOLATIONS_FILE_NAME: &str = "violations.json";
+
+pub const DEFAULT_SAFETY_FACTOR: f64 = 1f64;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Violation {
+    pub precompile: String,
+    #[serde(default)]
+    pub family: String,
+    pub scalar: u64,
+    #[serde(default)]
+    pub name: String,
+    pub label: String,
+    pub schedule: String,
+    pub measured_ns: u128,
+    pub measured_gas: u64,
+    // measured gas multiplied by the safety factor
+    pub required_gas: u64,
+    pub allowed_gas: u64
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ComplianceCheck {
+    pub schedules: Vec<String>,
+    pub safety_factor: f64,
+    pub num_checked: usize,
+    pub violations: Vec<Violation>
+}
+
+// Checks every measured vector against the selected schedules (all schedules present in the
+// results if `schedules` is empty). A vector complies if its measured gas multiplied by
+// `safety_factor` does not exceed the scheduled gas. Rejected inputs consume all the gas given
+// to them, so they are not checked and only reported by `report::summarize_rejections`
+pub fn check_compliance(all_results: &[MeasurementResults], schedules: &[String], safety_factor: f64) -> Result<ComplianceCheck, &'static str> {
+    if !safety_factor.is_finite() || safety_factor <= 0f64 {
+        return Err("safety factor must be positive");
+    }
+
+    let mut known_schedules = vec![];
+    for r in all_results.iter() {
+        known_schedules.extend(results::schedule_names(&r.records));
+    }
+    known_schedules.sort();
+    known_schedules.dedup();
+
+    let schedules = if schedules.is_empty() {
+        known_schedules
+    } else {
+        if schedules.iter().any(|s| !known_schedules.contains(s)) {
+            return Err("selected schedule is not present in the results");
+        }
+        schedules.to_vec()
+    };
+
+    let mut num_checked = 0;
+    let mut violations = vec![];
+    for r in all_results.iter() {
+        for record in r.records.iter().filter(|r| r.expected_error.is_none()) {
+            let required_gas = ((record.gas as f64) * safety_factor).ceil() as u64;
+            for schedule in schedules.iter() {
+                let allowed_gas = match record.schedule_gas.get(schedule) {
+                    Some(g) => *g,
+                    None => continue
+                };
+                num_checked += 1;
+                if required_gas > allowed_gas {
+                    violations.push(Violation {
+                        precompile: record.precompile.clone(),
+                        family: record.family.clone(),
+                        scalar: record.scalar,
+                        name: record.name.clone(),
+                        label: record.label.clone(),
+                        schedule: schedule.clone(),
+                        measured_ns: record.mean_ns,
+                        measured_gas: record.gas,
+                        required_gas,
+                        allowed_gas
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(ComplianceCheck {
+        schedules,
+        safety_factor,
+        num_checked,
+        violations
+    })
+}
+
+pub fn write_compliance_check(path: &str, check: &ComplianceCheck) -> std::io::Result<()> {
+    helpers::write_atomically(path, |file| {
+        serde_json::to_writer_pretty(file, check)?;
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn check_schedule_compliance() {
+        let vectors_path = std::env::var("BENCH_VECTORS_PATH").unwrap_or_else(|_| String::from("./vectors"));
+        let schedules: Vec<String> = std::env::var(SCHEDULES_ENV).ok()
+            .map(|s| s.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        let safety_factor = std::env::var(SAFETY_FACTOR_ENV).ok()
+            .map(|f| f.parse::<f64>().expect("safety factor must be a number"))
+            .unwrap_or(DEFAULT_SAFETY_FACTOR);
+        let output = std::env::var(VIOLATIONS_OUTPUT_ENV)
+            .unwrap_or_else(|_| format!("{}/{}", vectors_path, VIOLATIONS_FILE_NAME));
+
+        let all_results = results::find_results(&vectors_path).unwrap();
+        let check = check_compliance(&all_results, &schedules, safety_factor).unwrap();
+        write_compliance_check(&output, &check).unwrap();
+
+        println!("Checked {} (vector, schedule) pairs against {} with safety factor {}",
+            check.num_checked, check.schedules.join(", "), check.safety_factor);
+        for v in check.violations.iter() {
+            println!("{} for {} (vector {}) under {} schedule: requires {} gas ({} ns measured), allowed {}",
+                v.precompile, v.scalar, v.name, v.schedule, v.required_gas, v.measured_ns, v.allowed_gas);
+        }
+
+        assert!(check.violations.is_empty(), "{} schedule violation(s), see {}", check.violations.len(), output);
+    }
+}
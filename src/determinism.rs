@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+
+use super::generator::{self, DataPoint, RunParameters, VectorSelection};
+use super::manifest;
+use super::runners::PrecompileError;
+
+pub const PRECOMPILE_NAMES: [&str; 7] = ["sha256", "ripemd", "ecrecover", "blake2f", "bnadd", "bnmul", "bnpair"];
+
+// (input, output) pairs of every (family, parameter), in the order they were generated
+pub type VectorSet = BTreeMap<(String, u64), Vec<(Vec<u8>, Vec<u8>)>>;
+
+// Inputs of invalid vectors with their expected errors, keyed as `VectorSet`
+pub type InvalidVectorSet = BTreeMap<(String, u64), Vec<(Vec<u8>, PrecompileError)>>;
+
+// Generates vectors exactly as `generate_*` tests write them, but without measuring them.
+// Invalid vectors are left out since they are not written as CSV
+pub fn generate_vector_set(precompile: &str, params: &RunParameters) -> Result<VectorSet, &'static str> {
+    generate_vector_sets(precompile, params).map(|(set, _)| set)
+}
+
+// Valid and invalid vectors of `generate_*` tests
+pub fn generate_vector_sets(precompile: &str, params: &RunParameters) -> Result<(VectorSet, InvalidVectorSet), &'static str> {
+    let mut params = params.clone();
+    params.num_tries_per_vector = 0;
+    // selection depends on timings, so it can't be deterministic
+    params.selection = VectorSelection::All;
+
+    let sets = match precompile {
+        "sha256" => into_vector_sets(generator::generate_sha256_vectors(&params), |(i, o)| (i, o.to_vec())),
+        "ripemd" => into_vector_sets(generator::generate_ripemd_vectors(&params), |(i, o)| {
+            // output is written as a 32 byte word
+            let mut padded = vec![0u8; 12];
+            padded.extend_from_slice(&o[..]);
+
+            (i, padded)
+        }),
+        "ecrecover" => into_vector_sets(generator::generate_ecrecover_vectors(&params), |(i, o)| (i.to_vec(), generator::encode_ecrecover_output(&o))),
+        "blake2f" => into_vector_sets(generator::generate_blake2f_vectors(&params), |(i, o)| (i, o.to_vec())),
+        "bnadd" => into_vector_sets(generator::generate_bn_add_vectors(&params), |(i, o)| (i.to_vec(), o.to_vec())),
+        "bnmul" => into_vector_sets(generator::generate_bn_mul_vectors(&params), |(i, o)| (i.to_vec(), o.to_vec())),
+        "bnpair" => into_vector_sets(generator::generate_bnpair_vectors(&params), |(i, o)| (i, o.to_vec())),
+        _ => {
+            return Err("unknown precompile");
+        }
+    };
+
+    Ok(sets)
+}
+
+// Human readable differences between two vector sets, `a_name` and `b_name` describe their origin
+pub fn diff_vector_sets(a: &VectorSet, b: &VectorSet, a_name: &str, b_name: &str) -> Vec<String> {
+    let mut problems = vec![];
+
+    for (key, a_vectors) in a.iter() {
+        let scalar = describe_key(key);
+        let b_vectors = match b.get(key) {
+            Some(v) => v,
+            None => {
+                problems.push(format!("parameter {}: missing in {}", scalar, b_name));
+                continue;
+            }
+        };
+        if a_vectors.len() != b_vectors.len() {
+            problems.push(format!("parameter {}: {} has {} vectors, {} has {}", scalar, a_name, a_vectors.len(), b_name, b_vectors.len()));
+        }
+        for (i, (a_vector, b_vector)) in a_vectors.iter().zip(b_vectors.iter()).enumerate() {
+            if a_vector.0 != b_vector.0 {
+                problems.push(format!("parameter {}, vector {}: inputs differ", scalar, i));
+            } else if a_vector.1 != b_vector.1 {
+                problems.push(format!("parameter {}, vector {}: outputs differ for the same input", scalar, i));
+            }
+        }
+    }
+
+    for key in b.keys() {
+        if !a.contains_key(key) {
+            problems.push(format!("parameter {}: missing in {}", describe_key(key), a_name));
+        }
+    }
+
+    problems
+}
+
+fn describe_key(key: &(String, u64)) -> String {
+    let (family, scalar) = key;
+    if family.is_empty() {
+        scalar.to_string()
+    } else {
+        format!("{} ({})", scalar, family)
+    }
+}
+
+// Reads `{base_path}/current/input_param_scalar_{scalar}_gas_{gas}.csv` files of a vectors tree,
+// and the same files of other families from `{base_path}/current/{family}/`
+pub fn read_committed_vector_set(base_path: &str) -> std::io::Result<VectorSet> {
+    let mut set = VectorSet::new();
+
+    let dir = std::path::Path::new(base_path).join("current");
+    read_committed_family(&dir, "", &mut set)?;
+    for entry in std::fs::read_dir(&dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let family = entry.file_name().to_string_lossy().into_owned();
+            read_committed_family(&entry.path(), &family, &mut set)?;
+        }
+    }
+
+    Ok(set)
+}
+
+fn read_committed_family(dir: &std::path::Path, family: &str, set: &mut VectorSet) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+        let scalar = match manifest::parse_scalar_and_gas(&file_name) {
+            (Some(scalar), _) => scalar,
+            _ => continue
+        };
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_path(&path)?;
+        let vectors = set.entry((family.to_string(), scalar)).or_default();
+        for record in reader.records() {
+            let record = record?;
+            let decode = |field: Option<&str>| {
+                hex::decode(field.unwrap_or_default()).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            };
+            vectors.push((decode(record.get(0))?, decode(record.get(1))?));
+        }
+    }
+
+    Ok(())
+}
+
+pub const COMPARE_COMMITTED_ENV: &str = "BENCH_COMPARE_COMMITTED";
+
+// Generated vectors compared against the committed ones, only the default family by default.
+// Families other than the default one have no committed vectors unless the tree was regenerated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommittedComparison {
+    Off,
+    DefaultFamily,
+    AllFamilies
+}
+
+impl CommittedComparison {
+    pub fn parse(value: &str) -> Result<Self, &'static str> {
+        match value.trim() {
+            "0" | "off" => Ok(CommittedComparison::Off),
+            "" | "default" => Ok(CommittedComparison::DefaultFamily),
+            "1" | "all" => Ok(CommittedComparison::AllFamilies),
+            _ => Err("must be one of off, default or all")
+        }
+    }
+
+    pub fn from_env() -> Self {
+        match std::env::var(COMPARE_COMMITTED_ENV) {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|e| panic!("invalid {}: {}", COMPARE_COMMITTED_ENV, e)),
+            Err(_) => CommittedComparison::DefaultFamily
+        }
+    }
+
+    pub fn filter(&self, set: &VectorSet) -> VectorSet {
+        match self {
+            CommittedComparison::DefaultFamily => set.iter()
+                .filter(|((family, _), _)| family.is_empty())
+                .map(|(key, vectors)| (key.clone(), vectors.clone()))
+                .collect(),
+            _ => set.clone()
+        }
+    }
+}
+
+// Same input is written only once per parameter, see `RecordSpool`
+pub fn deduplicate(set: &VectorSet) -> VectorSet {
+    set.iter().map(|(key, vectors)| {
+        let mut seen = std::collections::HashSet::new();
+        let unique = vectors.iter()
+            .filter(|(input, _)| seen.insert(input.clone()))
+            .cloned()
+            .collect();
+
+        (key.clone(), unique)
+    }).collect()
+}
+
+fn into_vector_sets<T, C: Fn(T) -> (Vec<u8>, Vec<u8>)>(data_points: Vec<DataPoint<T>>, transformer: C) -> (VectorSet, InvalidVectorSet) {
+    let mut set = VectorSet::new();
+    let mut invalid_set = InvalidVectorSet::new();
+    for data_point in data_points.into_iter() {
+        let key = (data_point.family, data_point.scalar);
+        for v in data_point.vectors.into_iter() {
+            let (input, output) = transformer(v.vector);
+            match v.expected_error {
+                Some(e) => invalid_set.entry(key.clone()).or_default().push((input, e)),
+                None => set.entry(key.clone()).or_default().push((input, output))
+            }
+        }
+    }
+
+    (set, invalid_set)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn check_determinism() {
+        let vectors_path = std::env::var("BENCH_VECTORS_PATH").unwrap_or_else(|_| String::from("./vectors"));
+        let compare_committed = CommittedComparison::from_env();
+        // same number of vectors as the `generate_*` tests
+        let params = RunParameters::from_env(10, 0);
+
+        let mut all_problems = vec![];
+        for precompile in PRECOMPILE_NAMES.iter() {
+            let first = generate_vector_set(precompile, &params).unwrap();
+            let second = generate_vector_set(precompile, &params).unwrap();
+
+            let mut problems = diff_vector_sets(&first, &second, "first run", "second run");
+
+            let base_path = format!("{}/{}", vectors_path, precompile);
+            if compare_committed != CommittedComparison::Off && std::path::Path::new(&base_path).join("current").exists() {
+                let generated = compare_committed.filter(&deduplicate(&first));
+                let committed = compare_committed.filter(&read_committed_vector_set(&base_path).unwrap());
+                problems.extend(diff_vector_sets(&generated, &committed, "generated", "committed"));
+            }
+
+            println!("{}: {} problem(s)", precompile, problems.len());
+            for p in problems.into_iter() {
+                all_problems.push(format!("{}: {}", precompile, p));
+            }
+        }
+
+        for p in all_problems.iter() {
+            println!("{}", p);
+        }
+
+        assert!(all_problems.is_empty(), "vector generation is not deterministic or does not match committed vectors");
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Environment {
+    pub crate_version: String,
+    pub git_revision: Option<String>,
+    pub git_dirty: Option<bool>,
+    pub rustc_version: Option<String>,
+    pub optimized_build: bool,
+    pub os: String,
+    pub arch: String,
+    pub cpu_model: Option<String>,
+    pub num_cpus: usize,
+    pub rayon_threads: usize,
+    pub timestamp: u64
+}
+
+pub fn current_environment() -> Environment {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let num_cpus = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+
+    Environment {
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        git_revision: command_output("git", &["rev-parse", "HEAD"]),
+        git_dirty: command_output("git", &["status", "--porcelain", "--untracked-files=no"]).map(|s| !s.is_empty()),
+        rustc_version: command_output("rustc", &["--version"]),
+        optimized_build: !cfg!(debug_assertions),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        cpu_model: cpu_model(),
+        num_cpus,
+        rayon_threads: rayon::current_num_threads(),
+        timestamp
+    }
+}
+
+// Short single line description, e.g. for report headers
+pub fn describe(env: &Environment) -> String {
+    format!("{} ({}/{}, {} cpus), rustc: {}, revision: {}{}{}",
+        env.cpu_model.as_deref().unwrap_or("unknown cpu"),
+        env.os,
+        env.arch,
+        env.num_cpus,
+        env.rustc_version.as_deref().unwrap_or("unknown"),
+        env.git_revision.as_deref().unwrap_or("unknown"),
+        if env.git_dirty == Some(true) { " (dirty)" } else { "" },
+        if env.optimized_build { "" } else { ", UNOPTIMIZED build" }
+    )
+}
+
+fn command_output(command: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(command).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn cpu_model() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+
+    cpuinfo.lines()
+        .find(|l| l.starts_with("model name"))
+        .and_then(|l| l.split(':').nth(1))
+        .map(|m| m.trim().to_string())
+}
@@ -0,0 +1,207 @@
+use serde::{Serialize, Deserialize};
+
+use super::helpers;
+use super::results::{self, MeasurementResults};
+
+pub const FIT_FILE_NAME: &str = "fit.json";
+
+// Weighted least squares fit of `y = intercept + slope * x`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LinearFit {
+    pub intercept: f64,
+    pub slope: f64,
+    pub r_squared: f64,
+    pub num_points: usize
+}
+
+// Running time of a precompile split into a constant part and a part per unit of work,
+// e.g. per 64 byte block of SHA256 and RIPEMD160 or per round of Blake2f
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CostFit {
+    pub precompile: String,
+    pub unit: String,
+    pub gas_per_second: u128,
+    pub base_ns: f64,
+    pub per_unit_ns: f64,
+    pub base_gas: f64,
+    pub per_unit_gas: f64,
+    pub r_squared: f64,
+    pub num_points: usize
+}
+
+pub fn fit_linear(points: &[(f64, f64)]) -> Result<LinearFit, &'static str> {
+    let weighted: Vec<_> = points.iter().map(|(x, y)| (*x, *y, 1f64)).collect();
+
+    fit_linear_weighted(&weighted)
+}
+
+// Minimizes relative instead of absolute residuals, so that a few points of a large `x` (e.g.
+// millions of Blake2f rounds) don't hide the constant part measured at a small `x`
+pub fn fit_linear_relative(points: &[(f64, f64)]) -> Result<LinearFit, &'static str> {
+    let weighted: Vec<_> = points.iter().map(|(x, y)| (*x, *y, 1f64 / (y * y).max(1f64))).collect();
+
+    fit_linear_weighted(&weighted)
+}
+
+// Points are `(x, y, weight)`
+pub fn fit_linear_weighted(points: &[(f64, f64, f64)]) -> Result<LinearFit, &'static str> {
+    if points.len() < 2 {
+        return Err("at least two points are required");
+    }
+
+    let total_weight = points.iter().map(|(_, _, w)| w).sum::<f64>();
+    let mean_x = points.iter().map(|(x, _, w)| x * w).sum::<f64>() / total_weight;
+    let mean_y = points.iter().map(|(_, y, w)| y * w).sum::<f64>() / total_weight;
+
+    let mut sxx = 0f64;
+    let mut sxy = 0f64;
+    let mut syy = 0f64;
+    for (x, y, w) in points.iter() {
+        sxx += w * (x - mean_x) * (x - mean_x);
+        sxy += w * (x - mean_x) * (y - mean_y);
+        syy += w * (y - mean_y) * (y - mean_y);
+    }
+    if sxx == 0f64 {
+        return Err("at least two different x values are required");
+    }
+
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    // all y values are the same and are fitted exactly
+    let r_squared = if syy == 0f64 { 1f64 } else { (sxy * sxy) / (sxx * syy) };
+
+    Ok(LinearFit {
+        intercept,
+        slope,
+        r_squared,
+        num_points: points.len()
+    })
+}
+
+// Number of 64 byte blocks SHA256 and RIPEMD160 compress for an input of `len` bytes,
+// including the padding byte and the 8 byte length
+pub fn hash_blocks(len: u64) -> u64 {
+    (len + 8) / 64 + 1
+}
+
+fn blake2f_rounds(rounds: u64) -> u64 {
+    rounds
+}
+
+// Name of the unit of work and the number of units for a parameter of a precompile. Families
+// whose parameter means something else (e.g. an input length of invalid Blake2f inputs) are
+// not fitted
+pub type CostUnit = (&'static str, fn(u64) -> u64);
+
+pub fn cost_unit(precompile: &str, family: &str) -> Option<CostUnit> {
+    match (precompile, family) {
+        ("sha256", "") | ("ripemd", "") => Some(("64 byte block", hash_blocks)),
+        ("blake2f", "") | ("blake2f", "large_rounds") | ("blake2f", "final_block") | ("blake2f", "not_final_block") => Some(("round", blake2f_rounds)),
+        _ => None
+    }
+}
+
+// Fits the mean running time of every valid vector of fitted families against the number of
+// units of work of its parameter
+pub fn fit_costs(results: &MeasurementResults) -> Result<CostFit, &'static str> {
+    let (unit, _) = cost_unit(&results.precompile, "").ok_or("precompile has no cost model")?;
+
+    let points: Vec<(f64, f64)> = results.records.iter()
+        .filter(|r| r.expected_error.is_none())
+        .filter_map(|r| cost_unit(&r.precompile, &r.family).map(|(_, units_fn)| (units_fn(r.scalar) as f64, r.mean_ns as f64)))
+        .collect();
+    let fit = fit_linear_relative(&points)?;
+
+    let gas_per_ns = (results.gas_per_second as f64) / 1_000_000_000f64;
+
+    Ok(CostFit {
+        precompile: results.precompile.clone(),
+        unit: unit.to_string(),
+        gas_per_second: results.gas_per_second,
+        base_ns: fit.intercept,
+        per_unit_ns: fit.slope,
+        base_gas: fit.intercept * gas_per_ns,
+        per_unit_gas: fit.slope * gas_per_ns,
+        r_squared: fit.r_squared,
+        num_points: fit.num_points
+    })
+}
+
+pub fn write_fit(base_path: &str, fit: &CostFit) -> std::io::Result<()> {
+    let path = format!("{}/{}", base_path, FIT_FILE_NAME);
+    helpers::write_atomically(&path, |file| {
+        serde_json::to_writer_pretty(file, fit)?;
+
+        Ok(())
+    })
+}
+
+// Fits the timings written to `base_path` and writes the fit next to them
+pub fn fit_and_write(base_path: &str) -> std::io::Result<CostFit> {
+    let results = results::read_results(base_path)?;
+    let fit = fit_costs(&results).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    write_fit(base_path, &fit)?;
+
+    Ok(fit)
+}
+
+pub fn describe_fit(fit: &CostFit) -> String {
+    format!("{}: {:.0} ns + {:.1} ns per {} ({:.1} + {:.2} gas per {} at {} gas/second), R^2 = {:.4} over {} vectors",
+        fit.precompile, fit.base_ns, fit.per_unit_ns, fit.unit, fit.base_gas, fit.per_unit_gas, fit.unit, fit.gas_per_second, fit.r_squared, fit.num_points)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hash_blocks() {
+        let cases = [(0u64, 1u64), (1, 1), (55, 1), (56, 2), (63, 2), (64, 2), (119, 2), (120, 3), (128, 3), (183, 3), (184, 4)];
+        for (len, expected) in cases.iter() {
+            assert_eq!(hash_blocks(*len), *expected, "blocks for {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn test_fit_linear() {
+        let fit = fit_linear(&[(0f64, 100f64), (1f64, 110f64), (2f64, 120f64), (4f64, 140f64)]).unwrap();
+        assert!((fit.intercept - 100f64).abs() < 1e-9);
+        assert!((fit.slope - 10f64).abs() < 1e-9);
+        assert!((fit.r_squared - 1f64).abs() < 1e-9);
+        assert_eq!(fit.num_points, 4);
+
+        // duplicate points weigh as much as distinct ones
+        let fit = fit_linear(&[(0f64, 0f64), (0f64, 0f64), (0f64, 0f64), (1f64, 3f64)]).unwrap();
+        assert!((fit.slope - 3f64).abs() < 1e-9);
+        assert!(fit.intercept.abs() < 1e-9);
+
+        // constant time is fitted exactly
+        let fit = fit_linear(&[(1f64, 5f64), (2f64, 5f64), (3f64, 5f64)]).unwrap();
+        assert!(fit.slope.abs() < 1e-9);
+        assert_eq!(fit.r_squared, 1f64);
+
+        let fit = fit_linear(&[(0f64, 0f64), (1f64, 2f64), (2f64, 1f64)]).unwrap();
+        assert!(fit.r_squared > 0f64 && fit.r_squared < 1f64);
+
+        assert!(fit_linear(&[]).is_err());
+        assert!(fit_linear(&[(1f64, 1f64)]).is_err());
+        assert!(fit_linear(&[(1f64, 1f64), (1f64, 2f64)]).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn fit_precompile_costs() {
+        let vectors_path = std::env::var("BENCH_VECTORS_PATH").unwrap_or_else(|_| String::from("./vectors"));
+
+        let all_results = results::find_results(&vectors_path).unwrap();
+        for r in all_results.iter() {
+            if cost_unit(&r.precompile, "").is_none() {
+                continue;
+            }
+            match fit_costs(r) {
+                Ok(fit) => println!("{}", describe_fit(&fit)),
+                Err(e) => println!("{}: {}", r.precompile, e)
+            }
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use serde::{Serialize, Deserialize};
+
+use super::backends::Backend;
+use super::generator::RunParameters;
+use super::helpers;
+use super::runners::PrecompileError;
+use super::serialization::GethFailJsonFormat;
+
+pub const FUZZ_PRECOMPILES_ENV: &str = "BENCH_FUZZ_PRECOMPILES";
+pub const FUZZ_CASES_ENV: &str = "BENCH_FUZZ_CASES";
+pub const FUZZ_OUTPUT_ENV: &str = "BENCH_FUZZ_OUTPUT";
+pub const FUZZ_MAX_BLAKE2F_ROUNDS_ENV: &str = "BENCH_FUZZ_MAX_BLAKE2F_ROUNDS";
+
+pub const DEFAULT_FUZZ_CASES: u32 = 1000;
+pub const DEFAULT_FUZZ_MAX_BLAKE2F_ROUNDS: u32 = 16384;
+
+pub const REPRODUCERS_FILE_NAME: &str = "reproducers.csv";
+pub const DIVERGENCES_FILE_NAME: &str = "divergences.json";
+
+// Implementations check coordinates, curve and subgroup membership in different order, while the
+// precompile fails the same way for any invalid point. So errors are compared by the kind of input
+// they reject
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ErrorClass {
+    InvalidLength,
+    InvalidFlag,
+    InvalidPoint
+}
+
+pub fn error_class(error: &PrecompileError) -> ErrorClass {
+    match error {
+        PrecompileError::InvalidLength(_) | PrecompileError::InvalidPairingLength(_) => ErrorClass::InvalidLength,
+        PrecompileError::InvalidFlag(_) => ErrorClass::InvalidFlag,
+        PrecompileError::NotBelowModulus(_) | PrecompileError::NotOnCurve(_) | PrecompileError::NotInSubgroup(_) => ErrorClass::InvalidPoint
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Output(Vec<u8>),
+    Error(PrecompileError),
+    Panic(String)
+}
+
+impl Outcome {
+    // A panic never agrees with anything, even with a panic of another backend
+    pub fn agrees_with(&self, other: &Outcome) -> bool {
+        match (self, other) {
+            (Outcome::Output(a), Outcome::Output(b)) => a == b,
+            (Outcome::Error(a), Outcome::Error(b)) => error_class(a) == error_class(b),
+            _ => false
+        }
+    }
+
+    pub fn from_result(result: Result<Vec<u8>, PrecompileError>) -> Self {
+        match result {
+            Ok(output) => Outcome::Output(output),
+            Err(e) => Outcome::Error(e)
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Outcome::Output(output) => hex::encode(output),
+            Outcome::Error(e) => format!("error ({:?}): {}", error_class(e), e.message()),
+            Outcome::Panic(message) => format!("panic: {}", message)
+        }
+    }
+}
+
+// Panics are caught, so that a backend crashing on an input is reported as a divergence
+// instead of stopping the run
+pub fn run_backend(backend: &Backend, input: &[u8]) -> Outcome {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| (backend.run)(input))) {
+        Ok(result) => Outcome::from_result(result),
+        Err(payload) => {
+            let message = payload.downcast_ref::<&str>().map(|m| m.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+
+            Outcome::Panic(message)
+        }
+    }
+}
+
+pub fn run_backends(backends: &[Backend], input: &[u8]) -> Vec<(&'static str, Outcome)> {
+    backends.iter().map(|b| (b.name, run_backend(b, input))).collect()
+}
+
+pub fn outcomes_agree(outcomes: &[(&'static str, Outcome)]) -> bool {
+    match outcomes.split_first() {
+        Some(((_, first), rest)) => !matches!(first, Outcome::Panic(_)) && rest.iter().all(|(_, o)| o.agrees_with(first)),
+        None => true
+    }
+}
+
+// Keeps fuzzed Blake2f inputs cheap, mutations of a round count easily ask for billions of rounds
+pub fn bound_input(precompile: &str, mut input: Vec<u8>, max_blake2f_rounds: u32) -> Vec<u8> {
+    if precompile == "blake2f" && input.len() >= 4 {
+        let mut rounds = [0u8; 4];
+        rounds.copy_from_slice(&input[0..4]);
+        let rounds = u32::from_be_bytes(rounds) % (max_blake2f_rounds + 1);
+        input[0..4].copy_from_slice(&rounds.to_be_bytes());
+    }
+
+    input
+}
+
+// Inputs of all committed vectors of a precompile, see `determinism::read_committed_vector_set`
+pub fn read_seed_inputs(base_path: &str) -> std::io::Result<Vec<Vec<u8>>> {
+    let set = super::determinism::read_committed_vector_set(base_path)?;
+
+    Ok(unique_inputs(&set))
+}
+
+// Committed vectors of a precompile, or the ones `generate_*` tests would write if none are
+// committed (e.g. ECRECOVER families)
+pub fn seed_inputs(precompile: &str, vectors_path: &str, params: &RunParameters) -> std::io::Result<Vec<Vec<u8>>> {
+    let base_path = format!("{}/{}", vectors_path, precompile);
+    if std::path::Path::new(&base_path).join("current").exists() {
+        return read_seed_inputs(&base_path);
+    }
+
+    let set = super::determinism::generate_vector_set(precompile, params)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    Ok(unique_inputs(&set))
+}
+
+fn unique_inputs(set: &super::determinism::VectorSet) -> Vec<Vec<u8>> {
+    let mut seen = std::collections::HashSet::new();
+
+    set.values()
+        .flat_map(|vectors| vectors.iter().map(|(input, _)| input.clone()))
+        .filter(|input| seen.insert(input.clone()))
+        .collect()
+}
+
+// Input with the outcome of every backend on it
+pub type Reproducer = (Vec<u8>, Vec<(&'static str, Outcome)>);
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BackendOutcome {
+    pub backend: String,
+    pub outcome: String
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Divergence {
+    pub precompile: String,
+    pub input: String,
+    pub outcomes: Vec<BackendOutcome>
+}
+
+pub fn make_divergence(precompile: &str, input: &[u8], outcomes: &[(&'static str, Outcome)]) -> Divergence {
+    Divergence {
+        precompile: precompile.to_string(),
+        input: hex::encode(input),
+        outcomes: outcomes.iter().map(|(backend, outcome)| BackendOutcome {
+            backend: backend.to_string(),
+            outcome: outcome.describe()
+        }).collect()
+    }
+}
+
+// Writes minimized inputs as vectors, valid ones into `reproducers.csv` and invalid ones into
+// `fail-{precompile}.json`, with the outcome of the first backend that did not panic (the one
+// vectors are generated with, unless it crashed). Outcomes of all backends are written into
+// `divergences.json`
+pub fn write_reproducers(base_path: &str, precompile: &str, reproducers: &[Reproducer]) -> std::io::Result<()> {
+    std::fs::create_dir_all(base_path)?;
+
+    let mut valid = vec![];
+    let mut invalid = vec![];
+    for (i, (input, outcomes)) in reproducers.iter().enumerate() {
+        let reference = outcomes.iter().map(|(_, o)| o).find(|o| !matches!(o, Outcome::Panic(_)));
+        match reference {
+            Some(Outcome::Output(output)) => valid.push((input, output)),
+            Some(Outcome::Error(e)) => {
+                let name = format!("{}_fuzz_{}", precompile, i);
+                invalid.push(GethFailJsonFormat::new_from_data_and_name(input, e.geth_error(), name));
+            },
+            _ => {}
+        }
+    }
+
+    if !valid.is_empty() {
+        let path = format!("{}/{}", base_path, REPRODUCERS_FILE_NAME);
+        helpers::write_atomically(&path, |file| {
+            let mut writer = csv::Writer::from_writer(file);
+            for (input, output) in valid.iter() {
+                writer.write_record(&[hex::encode(input), hex::encode(output)])?;
+            }
+            writer.flush()
+        })?;
+    }
+
+    if !invalid.is_empty() {
+        let path = format!("{}/fail-{}.json", base_path, precompile);
+        helpers::write_atomically(&path, |file| {
+            serde_json::to_writer_pretty(file, &invalid)?;
+
+            Ok(())
+        })?;
+    }
+
+    let divergences: Vec<_> = reproducers.iter().map(|(input, outcomes)| make_divergence(precompile, input, outcomes)).collect();
+    let path = format!("{}/{}", base_path, DIVERGENCES_FILE_NAME);
+    helpers::write_atomically(&path, |file| {
+        serde_json::to_writer_pretty(file, &divergences)?;
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backends;
+    use crate::determinism::PRECOMPILE_NAMES;
+    use proptest::prelude::*;
+    use proptest::sample::Index;
+    use proptest::test_runner::{Config, RngAlgorithm, TestError, TestRng, TestRunner};
+
+    // Field modulus, group order and their neighbours, the edges of coordinate and scalar checks
+    const INTERESTING_WORDS: [&str; 7] = [
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd46",
+        "30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47",
+        "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000",
+        "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001",
+        "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
+    ];
+
+    const MAX_RANDOM_INPUT_LEN: usize = 512;
+    const MAX_EXTENSION_LEN: usize = 256;
+    const MAX_MUTATIONS: usize = 8;
+
+    #[derive(Clone, Debug)]
+    enum Mutation {
+        FlipBit(Index, u8),
+        SetByte(Index, u8),
+        // 32 byte aligned word, as all BN254 inputs consist of them
+        SetWord(Index, usize),
+        Truncate(Index),
+        Extend(Vec<u8>)
+    }
+
+    fn mutation() -> impl Strategy<Value = Mutation> {
+        prop_oneof![
+            (any::<Index>(), 0u8..8).prop_map(|(i, bit)| Mutation::FlipBit(i, bit)),
+            (any::<Index>(), any::<u8>()).prop_map(|(i, b)| Mutation::SetByte(i, b)),
+            (any::<Index>(), 0..INTERESTING_WORDS.len()).prop_map(|(i, w)| Mutation::SetWord(i, w)),
+            any::<Index>().prop_map(Mutation::Truncate),
+            prop::collection::vec(any::<u8>(), 1..MAX_EXTENSION_LEN).prop_map(Mutation::Extend)
+        ]
+    }
+
+    fn apply_mutation(input: &mut Vec<u8>, mutation: &Mutation) {
+        match mutation {
+            Mutation::FlipBit(i, bit) if !input.is_empty() => {
+                let i = i.index(input.len());
+                input[i] ^= 1 << bit;
+            },
+            Mutation::SetByte(i, b) if !input.is_empty() => {
+                let i = i.index(input.len());
+                input[i] = *b;
+            },
+            Mutation::SetWord(i, w) if input.len() >= 32 => {
+                let start = i.index(input.len() / 32) * 32;
+                let word = hex::decode(INTERESTING_WORDS[*w]).unwrap();
+                input[start..start+32].copy_from_slice(&word);
+            },
+            Mutation::Truncate(i) => {
+                let len = i.index(input.len() + 1);
+                input.truncate(len);
+            },
+            Mutation::Extend(bytes) => input.extend_from_slice(bytes),
+            _ => {}
+        }
+    }
+
+    // Mostly mutated committed vectors, which pass the first checks of a precompile much more
+    // often than random bytes do
+    fn fuzz_input(precompile: &'static str, seeds: Vec<Vec<u8>>, max_blake2f_rounds: u32) -> impl Strategy<Value = Vec<u8>> {
+        let mutated = (prop::sample::select(seeds), prop::collection::vec(mutation(), 1..MAX_MUTATIONS))
+            .prop_map(|(mut input, mutations)| {
+                for m in mutations.iter() {
+                    apply_mutation(&mut input, m);
+                }
+
+                input
+            });
+        let random = prop::collection::vec(any::<u8>(), 0..MAX_RANDOM_INPUT_LEN);
+
+        prop_oneof![4 => mutated, 1 => random]
+            .prop_map(move |input| bound_input(precompile, input, max_blake2f_rounds))
+    }
+
+    // Runs until the first divergence and returns it shrunk to a minimal input
+    fn fuzz_precompile(precompile: &'static str, seeds: Vec<Vec<u8>>, cases: u32, rng_seed: &[u8; 16], max_blake2f_rounds: u32) -> Option<Reproducer> {
+        let backends = backends::backends(precompile).unwrap();
+        let config = Config {
+            cases,
+            failure_persistence: None,
+            ..Config::default()
+        };
+        let mut runner = TestRunner::new_with_rng(config, TestRng::from_seed(RngAlgorithm::XorShift, rng_seed));
+
+        let result = runner.run(&fuzz_input(precompile, seeds, max_blake2f_rounds), |input| {
+            if outcomes_agree(&run_backends(&backends, &input)) {
+                Ok(())
+            } else {
+                Err(TestCaseError::fail("backends diverge"))
+            }
+        });
+
+        match result {
+            Ok(()) => None,
+            Err(TestError::Fail(_, input)) => {
+                let outcomes = run_backends(&backends, &input);
+                Some((input, outcomes))
+            },
+            Err(TestError::Abort(reason)) => panic!("fuzzing of {} aborted: {}", precompile, reason)
+        }
+    }
+
+    // A short run of the fuzzer on every `cargo test`, `fuzz_precompile_backends` runs it longer
+    const QUICK_FUZZ_CASES: u32 = 16;
+
+    #[test]
+    fn test_backends_agree_on_fuzzed_inputs() {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| {}));
+
+        let params = RunParameters::from_env(10, 0);
+
+        let mut divergences = vec![];
+        for precompile in PRECOMPILE_NAMES.iter() {
+            let seeds = seed_inputs(precompile, "./vectors", &params).unwrap();
+            if let Some((input, outcomes)) = fuzz_precompile(precompile, seeds, QUICK_FUZZ_CASES, &[0u8; 16], DEFAULT_FUZZ_MAX_BLAKE2F_ROUNDS) {
+                divergences.push(make_divergence(precompile, &input, &outcomes));
+            }
+        }
+
+        std::panic::set_hook(hook);
+
+        assert!(divergences.is_empty(), "backends diverge: {}", serde_json::to_string_pretty(&divergences).unwrap());
+    }
+
+    #[test]
+    #[ignore]
+    fn fuzz_precompile_backends() {
+        let vectors_path = std::env::var("BENCH_VECTORS_PATH").unwrap_or_else(|_| String::from("./vectors"));
+        let output_path = std::env::var(FUZZ_OUTPUT_ENV).unwrap_or_else(|_| format!("{}/fuzz", vectors_path));
+        let cases = std::env::var(FUZZ_CASES_ENV).ok()
+            .map(|c| c.parse::<u32>().expect("number of cases must be an integer"))
+            .unwrap_or(DEFAULT_FUZZ_CASES);
+        let max_blake2f_rounds = std::env::var(FUZZ_MAX_BLAKE2F_ROUNDS_ENV).ok()
+            .map(|r| r.parse::<u32>().expect("number of rounds must be an integer"))
+            .unwrap_or(DEFAULT_FUZZ_MAX_BLAKE2F_ROUNDS);
+        let precompiles: Vec<&'static str> = match std::env::var(FUZZ_PRECOMPILES_ENV) {
+            Ok(p) => PRECOMPILE_NAMES.iter().cloned().filter(|n| p.split(',').any(|p| p.trim() == *n)).collect(),
+            Err(_) => PRECOMPILE_NAMES.to_vec()
+        };
+        let params = RunParameters::from_env(10, 0);
+
+        // backends that panic are reported, the default hook would print every caught panic
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| {}));
+
+        let mut divergent = vec![];
+        for precompile in precompiles.into_iter() {
+            let seeds = seed_inputs(precompile, &vectors_path, &params).unwrap();
+            assert!(!seeds.is_empty(), "{} has no vectors to seed from", precompile);
+
+            let num_seeds = seeds.len();
+            let divergence = fuzz_precompile(precompile, seeds, cases, &params.rng_seed, max_blake2f_rounds);
+            match divergence {
+                None => println!("{}: {} cases from {} seeds, backends agree", precompile, cases, num_seeds),
+                Some((input, outcomes)) => {
+                    println!("{}: backends diverge on {}", precompile, hex::encode(&input));
+                    for (backend, outcome) in outcomes.iter() {
+                        println!("    {}: {}", backend, outcome.describe());
+                    }
+                    let base_path = format!("{}/{}", output_path, precompile);
+                    write_reproducers(&base_path, precompile, &[(input, outcomes)]).unwrap();
+                    divergent.push(precompile);
+                }
+            }
+        }
+
+        std::panic::set_hook(hook);
+
+        assert!(divergent.is_empty(), "backends of {:?} diverge, reproducers are in {}", divergent, output_path);
+    }
+}
@@ -0,0 +1,174 @@
+use super::pricers::{self, Pricer};
+
+pub const GAS_TARGETS_ENV: &str = "BENCH_GAS_TARGETS";
+pub const GAS_TARGET_SCHEDULE_ENV: &str = "BENCH_GAS_TARGET_SCHEDULE";
+
+// Gas of a large transaction, and of a block under older and current gas limits
+pub const DEFAULT_GAS_TARGETS: [u64; 3] = [1_000_000, 10_000_000, 30_000_000];
+
+// Precompiles with a price that depends on the parameter
+pub const VARIABLE_PRICE_PRECOMPILES: [&str; 4] = ["sha256", "ripemd", "blake2f", "bnpair"];
+
+// Parameter of a precompile priced closest to a gas target under a schedule
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GasTarget {
+    pub target_gas: u64,
+    pub scalar: u64,
+    pub gas: u64
+}
+
+pub fn schedule_pricer(precompile: &str, schedule: &str) -> Result<Pricer, &'static str> {
+    let pricer = match (precompile, schedule) {
+        ("sha256", "current") => pricers::current_sha256_pricer(),
+        ("sha256", "proposed") => pricers::proposed_sha256_pricer(),
+        ("ripemd", "current") => pricers::current_ripemd_pricer(),
+        ("ripemd", "proposed") => pricers::proposed_ripemd_pricer(),
+        ("blake2f", "current") | ("blake2f", "proposed") => pricers::blake2f_pricer(),
+        ("bnadd", "current") => pricers::current_bnadd_pricer(),
+        ("bnadd", "proposed") => pricers::proposed_bnadd_pricer(),
+        ("bnmul", "current") => pricers::current_bnmul_pricer(),
+        ("bnmul", "proposed") => pricers::proposed_bnmul_pricer(),
+        ("bnpair", "current") | ("bnpair", "proposed") => pricers::bnpair_pricer(),
+        ("sha256", _) | ("ripemd", _) | ("blake2f", _) | ("bnadd", _) | ("bnmul", _) | ("bnpair", _) => {
+            return Err("unknown schedule");
+        },
+        _ => {
+            return Err("unknown precompile");
+        }
+    };
+
+    Ok(pricer)
+}
+
+// Largest parameter the input format of a precompile can encode
+fn max_scalar(precompile: &str) -> u64 {
+    match precompile {
+        "blake2f" => u32::MAX as u64,
+        _ => u64::MAX
+    }
+}
+
+// Inverts the schedule's pricer for every target, the same parameter is listed once
+pub fn gas_targets(precompile: &str, schedule: &str, targets: &[u64]) -> Result<Vec<GasTarget>, &'static str> {
+    let pricer = schedule_pricer(precompile, schedule)?;
+
+    let mut result: Vec<GasTarget> = vec![];
+    for target_gas in targets.iter().cloned() {
+        let scalar = pricer.closest_scalar(target_gas).ok_or("price of the precompile is constant")?;
+        if scalar > max_scalar(precompile) {
+            return Err("gas target is above the price of the largest parameter");
+        }
+        if result.iter().any(|t| t.scalar == scalar) {
+            continue;
+        }
+        result.push(GasTarget {
+            target_gas,
+            scalar,
+            gas: pricer.price(scalar)
+        });
+    }
+
+    Ok(result)
+}
+
+pub fn gas_targets_from_env() -> Vec<u64> {
+    match std::env::var(GAS_TARGETS_ENV) {
+        Ok(targets) => targets.split(',')
+            .map(|t| t.trim().replace('_', ""))
+            .filter(|t| !t.is_empty())
+            .map(|t| t.parse::<u64>().unwrap_or_else(|_| panic!("{} must be a list of integers", GAS_TARGETS_ENV)))
+            .collect(),
+        Err(_) => DEFAULT_GAS_TARGETS.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::generator::*;
+    use crate::spool::RecordSpool;
+
+    // Few vectors of a block-sized work take seconds to measure
+    const NUM_VECTORS: usize = 3;
+    const NUM_TRIES_PER_VECTOR: usize = 10;
+
+    fn do_gas_targets(precompile: &str) {
+        let schedule = std::env::var(GAS_TARGET_SCHEDULE_ENV).unwrap_or_else(|_| String::from("current"));
+        // kept apart from the regular vectors, so they are not mixed into manifests and timing results,
+        // and ignored by git since block-sized inputs take tens of megabytes
+        let base_path = format!("./vectors/gas_targets/{}", precompile);
+
+        let params = RunParameters::from_env(NUM_VECTORS, NUM_TRIES_PER_VECTOR);
+        let run_started = std::time::SystemTime::now();
+        let targets = gas_targets(precompile, &schedule, &gas_targets_from_env()).unwrap();
+        for t in targets.iter() {
+            println!("{}: parameter {} costs {} gas under {} schedule for a target of {} gas", precompile, t.scalar, t.gas, schedule, t.target_gas);
+        }
+        let scalars: Vec<u64> = targets.iter().map(|t| t.scalar).collect();
+
+        let ann_fn = |scalar: u64| {
+            let target = targets.iter().find(|t| t.scalar == scalar).expect("only targeted parameters are measured");
+            format!("For parameter {} ({} gas target under {} schedule):", scalar, target.target_gas, schedule)
+        };
+
+        let spool = RecordSpool::shared(precompile);
+        let csv_writer_fn = make_csv_writer_for_path(&base_path, &spool);
+        let json_writer_fn = make_json_writer_for_path_and_test_name(&base_path, precompile, &spool);
+        let timings_writer_fn = make_timings_writer_for_path_and_test_name(&base_path, precompile, params.gas_rate.gas_per_second);
+        let writers = vec![csv_writer_fn, json_writer_fn, timings_writer_fn];
+
+        let (current_pricer, proposed_pricer) = (schedule_pricer(precompile, "current").unwrap(), schedule_pricer(precompile, "proposed").unwrap());
+        let gas_per_second = params.gas_rate.gas_per_second;
+        let result = match precompile {
+            "sha256" => {
+                let lengths: Vec<usize> = scalars.iter().map(|s| *s as usize).collect();
+                perform_measurements(true, current_pricer, proposed_pricer, gas_per_second,
+                    || generate_sha256_vectors_for_lengths(&params, &lengths),
+                    |(i, o): (Vec<u8>, [u8; 32])| (i, o.to_vec()),
+                    writers, ann_fn)
+            },
+            "ripemd" => {
+                let lengths: Vec<usize> = scalars.iter().map(|s| *s as usize).collect();
+                perform_measurements(true, current_pricer, proposed_pricer, gas_per_second,
+                    || generate_ripemd_vectors_for_lengths(&params, &lengths),
+                    |(i, o): (Vec<u8>, [u8; 20])| {
+                        let mut padded = vec![0u8; 12];
+                        padded.extend_from_slice(&o[..]);
+
+                        (i, padded)
+                    },
+                    writers, ann_fn)
+            },
+            "blake2f" => {
+                perform_measurements(true, current_pricer, proposed_pricer, gas_per_second,
+                    || generate_blake2f_vectors_for_rounds(&params, &scalars),
+                    |(i, o): (Vec<u8>, [u8; 64])| (i, o.to_vec()),
+                    writers, ann_fn)
+            },
+            "bnpair" => {
+                let num_pairs: Vec<usize> = scalars.iter().map(|s| *s as usize).collect();
+                perform_measurements(true, current_pricer, proposed_pricer, gas_per_second,
+                    || generate_bnpair_vectors_for_pairs(&params, &num_pairs),
+                    |(i, o): (Vec<u8>, [u8; 32])| (i, o.to_vec()),
+                    writers, ann_fn)
+            },
+            _ => unreachable!("only precompiles with a variable price have gas targets")
+        };
+        result.expect("vectors must be written");
+
+        crate::manifest::write_manifest(&base_path, precompile, &params, &["current", "proposed"], run_started).expect("manifest must be written");
+    }
+
+    #[test]
+    #[ignore]
+    fn generate_gas_target_vectors() {
+        let precompiles: Vec<String> = std::env::var("BENCH_GAS_TARGET_PRECOMPILES").ok()
+            .map(|p| p.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+            .unwrap_or_else(|| VARIABLE_PRICE_PRECOMPILES.iter().map(|p| p.to_string()).collect());
+
+        for precompile in precompiles.iter() {
+            assert!(VARIABLE_PRICE_PRECOMPILES.contains(&precompile.as_str()), "{} has a constant price", precompile);
+            do_gas_targets(precompile);
+        }
+    }
+}
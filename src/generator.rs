@@ -1,24 +1,458 @@
-use super::{runners, input_generators, measurements, serialization};
+use super::{runners, input_generators, measurements, serialization, state_test, helpers, results, environment};
+use super::runners::PrecompileError;
+use super::measurements::TimingStats;
+use super::spool::SharedSpool;
+use super::calibration::GasRate;
 
 pub const MGAS_PER_SECOND: u128 = 30_000_000;
 
+pub const DEFAULT_RNG_SEED: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
 use rand::{SeedableRng};
 use rand_xorshift::XorShiftRng;
 use indicatif::{ProgressBar, ProgressStyle};
 use serde_json::to_writer;
+use serde::{Serialize, Deserialize};
+
+// Parameters of a generation run that are recorded into the manifest
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RunParameters {
+    pub num_different_vectors: usize,
+    pub num_tries_per_vector: usize,
+    pub rng_seed: [u8; 16],
+    pub gas_rate: GasRate,
+    pub selection: VectorSelection,
+    // input lengths of SHA256 and RIPEMD160 vectors
+    #[serde(default)]
+    pub lengths: LengthSweep,
+    // Blake2f vectors with more rounds are not generated
+    #[serde(default = "default_max_blake2f_rounds")]
+    pub max_blake2f_rounds: u64
+}
+
+impl RunParameters {
+    pub fn new(num_different_vectors: usize, num_tries_per_vector: usize, gas_rate: GasRate) -> Self {
+        Self {
+            num_different_vectors,
+            num_tries_per_vector,
+            rng_seed: DEFAULT_RNG_SEED,
+            gas_rate,
+            selection: VectorSelection::All,
+            lengths: LengthSweep::default(),
+            max_blake2f_rounds: DEFAULT_MAX_BLAKE2F_ROUNDS
+        }
+    }
+
+    // Run options that are set through the environment, see README
+    pub fn from_env(num_different_vectors: usize, num_tries_per_vector: usize) -> Self {
+        let mut params = Self::new(num_different_vectors, num_tries_per_vector, crate::calibration::gas_rate_from_env());
+        params.rng_seed = rng_seed_from_env();
+        params.selection = VectorSelection::from_env(num_different_vectors);
+        params.lengths = LengthSweep::from_env();
+        params.max_blake2f_rounds = std::env::var(MAX_BLAKE2F_ROUNDS_ENV).ok()
+            .map(|r| r.replace('_', "").parse::<u64>().expect("maximal number of rounds must be an integer"))
+            .unwrap_or(DEFAULT_MAX_BLAKE2F_ROUNDS);
+
+        params
+    }
+
+    // Number of vectors generated and measured per parameter before selection
+    pub fn num_candidates(&self) -> usize {
+        match self.selection {
+            VectorSelection::All => self.num_different_vectors,
+            VectorSelection::Slowest { num_candidates } | VectorSelection::Stratified { num_candidates } => {
+                std::cmp::max(num_candidates, self.num_different_vectors)
+            }
+        }
+    }
+}
+
+pub const SELECTION_ENV: &str = "BENCH_SELECTION";
+pub const NUM_CANDIDATES_ENV: &str = "BENCH_CANDIDATES";
+
+// Which of the measured vectors of every parameter are written. Selections other than `All`
+// generate a pool of `num_candidates` vectors and keep `num_different_vectors` of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum VectorSelection {
+    All,
+    // slowest vectors only
+    Slowest { num_candidates: usize },
+    // vectors spread evenly by running time from the fastest to the slowest one
+    Stratified { num_candidates: usize }
+}
+
+impl VectorSelection {
+    // `BENCH_SELECTION` is one of `all`, `slowest` or `stratified` and `BENCH_CANDIDATES` is a pool
+    // size, ten times the number of kept vectors by default
+    pub fn from_env(num_different_vectors: usize) -> Self {
+        let num_candidates = std::env::var(NUM_CANDIDATES_ENV).ok()
+            .map(|n| n.parse::<usize>().unwrap_or_else(|_| panic!("{} must be an integer", NUM_CANDIDATES_ENV)))
+            .unwrap_or(num_different_vectors * 10);
+
+        match std::env::var(SELECTION_ENV).as_deref() {
+            Err(_) | Ok("") | Ok("all") => VectorSelection::All,
+            Ok("slowest") => VectorSelection::Slowest { num_candidates },
+            Ok("stratified") => VectorSelection::Stratified { num_candidates },
+            Ok(other) => panic!("unknown {} value {}, expected all, slowest or stratified", SELECTION_ENV, other)
+        }
+    }
+}
+
+pub const LENGTHS_ENV: &str = "BENCH_LENGTHS";
+pub const MAX_BLAKE2F_ROUNDS_ENV: &str = "BENCH_BLAKE2F_MAX_ROUNDS";
+
+// Largest number of rounds of the Blake2f benchmark, so that a plain `cargo test` stays fast.
+// Transaction and block sized vectors (up to u32::MAX rounds, about a minute per call) are
+// generated only with a larger `BENCH_BLAKE2F_MAX_ROUNDS`
+pub const DEFAULT_MAX_BLAKE2F_ROUNDS: u64 = 8192;
+
+// Blake2f vectors above this number of rounds are measured fewer times, so that measuring one
+// takes about as long as for this number of rounds, but at least `MIN_TRIES_PER_VECTOR` times
+pub const BLAKE2F_FULL_TRIES_ROUNDS: u64 = 1024;
+pub const MIN_TRIES_PER_VECTOR: usize = 10;
+
+fn default_max_blake2f_rounds() -> u64 {
+    DEFAULT_MAX_BLAKE2F_ROUNDS
+}
+
+fn num_tries_for_blake2f_rounds(num_tries: usize, rounds: u64) -> usize {
+    if rounds <= BLAKE2F_FULL_TRIES_ROUNDS {
+        return num_tries;
+    }
+    let scaled = ((num_tries as u64) * BLAKE2F_FULL_TRIES_ROUNDS / rounds) as usize;
+
+    std::cmp::min(num_tries, std::cmp::max(scaled, MIN_TRIES_PER_VECTOR))
+}
+
+// Lengths around the first, second and third 64 byte block of SHA256 and RIPEMD160: one byte
+// below and above the last length that still fits the padding (0x80 and an 8 byte length) into
+// the block and the first one that does not, and one byte around the multiple of 64
+pub const HASH_BLOCK_BOUNDARIES: [usize; 21] = [
+    54, 55, 56, 57, 63, 64, 65,
+    118, 119, 120, 121, 127, 128, 129,
+    182, 183, 184, 185, 191, 192, 193
+];
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LengthRange {
+    // start, start + step, ... while not above end
+    Linear { start: usize, end: usize, step: usize },
+    // start, start * factor, ... while not above end
+    Geometric { start: usize, end: usize, factor: usize },
+    Explicit(Vec<usize>)
+}
+
+// Union of length ranges, 0..=256 with step 8 by default
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LengthSweep {
+    pub ranges: Vec<LengthRange>
+}
+
+impl Default for LengthSweep {
+    fn default() -> Self {
+        Self {
+            ranges: vec![LengthRange::Linear { start: 0, end: 256, step: 8 }]
+        }
+    }
+}
+
+impl LengthSweep {
+    // Sorted lengths without duplicates
+    pub fn lengths(&self) -> Vec<usize> {
+        let mut lengths = vec![];
+        for range in self.ranges.iter() {
+            match range {
+                LengthRange::Linear { start, end, step } => {
+                    lengths.extend((*start..=*end).step_by(*step));
+                },
+                LengthRange::Geometric { start, end, factor } => {
+                    let mut length = *start;
+                    while length <= *end {
+                        lengths.push(length);
+                        length = match length.checked_mul(*factor) {
+                            Some(l) => l,
+                            None => break
+                        };
+                    }
+                },
+                LengthRange::Explicit(explicit) => {
+                    lengths.extend(explicit.iter().cloned());
+                }
+            }
+        }
+        lengths.sort_unstable();
+        lengths.dedup();
+
+        lengths
+    }
 
-pub fn generate_sha256_vectors(num_different_vectors: usize, num_tries_per_vector: usize) -> Vec<(u64, Vec<(Vec<u8>, [u8; 32])>, u128, u64)> {    
-    let limit = 256;
-    let step = 8;
+    // Comma separated list of `a..=b:step` (linear), `a..=b*factor` (geometric), single lengths
+    // and `boundaries` for `HASH_BLOCK_BOUNDARIES`, e.g. `0..=256:8,boundaries,512..=1048576*2`
+    pub fn parse(spec: &str) -> Result<Self, &'static str> {
+        let parse_number = |n: &str| n.trim().replace('_', "").parse::<usize>().map_err(|_| "length must be a non-negative integer");
+
+        let mut ranges = vec![];
+        let mut explicit = vec![];
+        for item in spec.split(',').map(|i| i.trim()).filter(|i| !i.is_empty()) {
+            if item == "boundaries" {
+                explicit.extend_from_slice(&HASH_BLOCK_BOUNDARIES);
+                continue;
+            }
 
-    let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+            let (start, rest) = match item.find("..=") {
+                Some(position) => (parse_number(&item[..position])?, &item[(position + 3)..]),
+                None => {
+                    explicit.push(parse_number(item)?);
+                    continue;
+                }
+            };
 
+            let range = if let Some(position) = rest.find('*') {
+                let factor = parse_number(&rest[(position + 1)..])?;
+                if factor < 2 || start == 0 {
+                    return Err("geometric range must start above 0 and have a factor of at least 2");
+                }
+                LengthRange::Geometric { start, end: parse_number(&rest[..position])?, factor }
+            } else if let Some(position) = rest.find(':') {
+                let step = parse_number(&rest[(position + 1)..])?;
+                if step == 0 {
+                    return Err("step must be positive");
+                }
+                LengthRange::Linear { start, end: parse_number(&rest[..position])?, step }
+            } else {
+                LengthRange::Linear { start, end: parse_number(rest)?, step: 1 }
+            };
+            ranges.push(range);
+        }
+
+        if !explicit.is_empty() {
+            ranges.push(LengthRange::Explicit(explicit));
+        }
+        let sweep = Self { ranges };
+        if sweep.lengths().is_empty() {
+            return Err("length sweep is empty");
+        }
+
+        Ok(sweep)
+    }
+
+    pub fn from_env() -> Self {
+        match std::env::var(LENGTHS_ENV) {
+            Ok(spec) => Self::parse(&spec).unwrap_or_else(|e| panic!("invalid {}: {}", LENGTHS_ENV, e)),
+            Err(_) => Self::default()
+        }
+    }
+}
+
+// Labels every kept vector: by its position for `All`, by its rank from the slowest one
+// (`slowest_0`, `slowest_1`, ...) for `Slowest` and by its running time percentile
+// (`fastest`, `p25`, `median`, ..., `slowest`) for `Stratified`
+pub fn select_vectors<T>(candidates: Vec<MeasuredVector<T>>, params: &RunParameters) -> Vec<MeasuredVector<T>> {
+    let num_kept = std::cmp::min(params.num_different_vectors, candidates.len());
+    let labelled = |mut v: MeasuredVector<T>, label: String| {
+        v.label = label;
+
+        v
+    };
+
+    match params.selection {
+        VectorSelection::All => {
+            candidates.into_iter().enumerate().map(|(i, v)| labelled(v, i.to_string())).collect()
+        },
+        VectorSelection::Slowest { .. } => {
+            let mut candidates = candidates;
+            candidates.sort_by_key(|c| std::cmp::Reverse(c.timing.mean_ns));
+            candidates.truncate(num_kept);
+
+            candidates.into_iter().enumerate().map(|(i, v)| labelled(v, format!("slowest_{}", i))).collect()
+        },
+        VectorSelection::Stratified { .. } => {
+            let mut candidates: Vec<_> = candidates.into_iter().map(Some).collect();
+            candidates.sort_by_key(|c| c.as_ref().map(|v| v.timing.mean_ns));
+
+            let last = candidates.len().saturating_sub(1);
+            let mut selected = vec![];
+            for i in 0..num_kept {
+                // a single kept vector is the slowest one
+                let (position, percentile) = if num_kept == 1 {
+                    (last, 100)
+                } else {
+                    (i * last / (num_kept - 1), i * 100 / (num_kept - 1))
+                };
+                if let Some(v) = candidates[position].take() {
+                    let label = match percentile {
+                        0 => String::from("fastest"),
+                        50 => String::from("median"),
+                        100 => String::from("slowest"),
+                        p => format!("p{}", p)
+                    };
+                    selected.push(labelled(v, label));
+                }
+            }
+
+            selected
+        }
+    }
+}
+
+// Every vector of a family is generated from its own RNG, so any single vector can be regenerated
+// from its seed without generating the ones before it
+pub fn derive_vector_seed(rng_seed: &[u8; 16], family: &str, scalar: u64, index: usize) -> [u8; 16] {
+    let mut preimage = rng_seed.to_vec();
+    preimage.extend_from_slice(family.as_bytes());
+    preimage.extend_from_slice(&scalar.to_be_bytes());
+    preimage.extend_from_slice(&(index as u64).to_be_bytes());
+
+    let digest = runners::run_sha256(&preimage);
+    let mut seed = [0u8; 16];
+    seed.copy_from_slice(&digest[0..16]);
+
+    seed
+}
+
+// Vectors of the default family are generated one after another from a single RNG seeded with the
+// run seed, as the committed vectors were. Their seed is the state of that RNG right before the
+// vector, an RNG seeded with it continues the same sequence
+pub fn rng_state(rng: &XorShiftRng) -> [u8; 16] {
+    #[derive(Deserialize)]
+    struct State {
+        x: u32,
+        y: u32,
+        z: u32,
+        w: u32
+    }
+
+    let state: State = serde_json::to_value(rng).and_then(serde_json::from_value).expect("RNG state is four words");
+    let mut seed = [0u8; 16];
+    for (i, word) in [state.x, state.y, state.z, state.w].iter().enumerate() {
+        seed[i*4..(i+1)*4].copy_from_slice(&word.to_le_bytes());
+    }
+
+    seed
+}
+
+pub const SEED_ENV: &str = "BENCH_SEED";
+
+// `BENCH_SEED` is either 32 hex characters or a decimal number, `DEFAULT_RNG_SEED` otherwise
+pub fn rng_seed_from_env() -> [u8; 16] {
+    match std::env::var(SEED_ENV) {
+        Ok(seed) => parse_rng_seed(&seed).unwrap_or_else(|e| panic!("invalid {}: {}", SEED_ENV, e)),
+        Err(_) => DEFAULT_RNG_SEED
+    }
+}
+
+pub fn parse_rng_seed(seed: &str) -> Result<[u8; 16], &'static str> {
+    let seed = seed.trim();
+    if let Some(encoding) = seed.strip_prefix("0x").or(if seed.len() == 32 { Some(seed) } else { None }) {
+        let decoded = hex::decode(encoding).map_err(|_| "seed is not a valid hex")?;
+        if decoded.len() != 16 {
+            return Err("hex seed must be 16 bytes long");
+        }
+        let mut result = [0u8; 16];
+        result.copy_from_slice(&decoded);
+
+        return Ok(result);
+    }
+
+    let number = seed.parse::<u128>().map_err(|_| "seed must be 16 hex encoded bytes or a decimal number")?;
+
+    Ok(number.to_be_bytes())
+}
+
+// Input and expected output, or an error for invalid inputs
+pub type RegeneratedVector = (Vec<u8>, Result<Vec<u8>, PrecompileError>);
+
+// Single vector of `generate_*_vectors` with a given seed
+pub fn regenerate_vector(precompile: &str, family: &str, scalar: u64, seed: [u8; 16]) -> Result<RegeneratedVector, &'static str> {
+    let mut rng = XorShiftRng::from_seed(seed);
+
+    if !family.is_empty() {
+        let vector = match precompile {
+            "bnadd" => {
+                let family = input_generators::BnAddFamily::from_name(family).ok_or("unknown vector family")?;
+                let (input, output) = input_generators::generate_bnadd_vector_for_family(family, &mut rng);
+                (input.to_vec(), output.map(|o| o.to_vec()))
+            },
+            "bnpair" => {
+                let family = input_generators::BnPairFamily::from_name(family).ok_or("unknown vector family")?;
+                let (input, output) = input_generators::generate_bnpair_vector_for_family(family, scalar as usize, &mut rng);
+                (input, output.map(|o| o.to_vec()))
+            },
+            "blake2f" => {
+                let family = input_generators::Blake2fFamily::from_name(family).ok_or("unknown vector family")?;
+                if !family.params().contains(&scalar) {
+                    return Err("unknown parameter of the vector family");
+                }
+                let (input, output) = input_generators::generate_blake2f_vector_for_family(family, scalar, &mut rng);
+                (input, output.map(|o| o.to_vec()))
+            },
+            "bnmul" => {
+                let family = input_generators::BnMulFamily::from_name(family).ok_or("unknown vector family")?;
+                if !family.params().contains(&scalar) {
+                    return Err("unknown parameter of the vector family");
+                }
+                let (input, output) = input_generators::generate_bnmul_vector_for_family(family, scalar, &mut rng);
+                (input.to_vec(), output.map(|o| o.to_vec()))
+            },
+            "ecrecover" => {
+                let family = input_generators::EcrecoverFamily::from_name(family).ok_or("unknown vector family")?;
+                let (input, output) = input_generators::generate_ecrecover_vector_for_family(family, &mut rng);
+                (input.to_vec(), Ok(output.to_vec()))
+            },
+            _ => {
+                return Err("unknown vector family");
+            }
+        };
+
+        return Ok(vector);
+    }
+
+    let vector = match precompile {
+        "sha256" => {
+            let (input, output) = input_generators::generate_sha256_vector_for_len(scalar as usize, &mut rng);
+            (input, output.to_vec())
+        },
+        "ripemd" => {
+            let (input, output) = input_generators::generate_ripemd_vector_for_len(scalar as usize, &mut rng);
+            (input, output.to_vec())
+        },
+        "blake2f" => {
+            let (input, output) = input_generators::generate_blake2f_vector_for_num_rounds(scalar as usize, &mut rng);
+            (input, output.to_vec())
+        },
+        "bnadd" => {
+            let (input, output) = input_generators::generate_bnadd_vector(&mut rng);
+            (input.to_vec(), output.to_vec())
+        },
+        "bnmul" => {
+            let (input, output) = input_generators::generate_bnmul_vector(&mut rng);
+            (input.to_vec(), output.to_vec())
+        },
+        "bnpair" => {
+            let (input, output) = input_generators::generate_bnpair_vector(scalar as usize, &mut rng);
+            (input, output.to_vec())
+        },
+        _ => {
+            return Err("unknown precompile");
+        }
+    };
+
+    Ok((vector.0, Ok(vector.1)))
+}
+
+pub fn generate_sha256_vectors(params: &RunParameters) -> Vec<DataPoint<(Vec<u8>, [u8; 32])>> {    
+    generate_sha256_vectors_for_lengths(params, &params.lengths.lengths())
+}
+
+pub fn generate_sha256_vectors_for_lengths(params: &RunParameters, lengths: &[usize]) -> Vec<DataPoint<(Vec<u8>, [u8; 32])>> {    
     let mut data_points = vec![];
 
-    for len in (0..=limit).step_by(step) {
-        let mut total = 0u128;
+    let mut rng = XorShiftRng::from_seed(params.rng_seed);
+
+    for len in lengths.iter().cloned() {
         let mut inputs_and_outputs = vec![];
-        for _ in 0..num_different_vectors {
+        for _ in 0..params.num_candidates() {
+            let seed = rng_state(&rng);
             let (input, output) = input_generators::generate_sha256_vector_for_len(len, &mut rng);
 
             let input_clone = input.clone();
@@ -30,34 +464,33 @@ pub fn generate_sha256_vectors(num_different_vectors: usize, num_tries_per_vecto
                 r == output
             };
 
-            let total_time = measurements::measure_with_validity(&runnable, &checker, num_tries_per_vector);
-            total += total_time;
-            inputs_and_outputs.push((input, output));
+            let samples = measurements::measure_samples_with_validity(&runnable, &checker, params.num_tries_per_vector, None);
+            inputs_and_outputs.push(MeasuredVector::new((input, output), TimingStats::from_samples(&samples), seed));
         }
 
-        let average_ns = total / (num_different_vectors as u128) / (num_tries_per_vector as u128);
-        let gas = average_ns * MGAS_PER_SECOND / 1_000_000_000;
-
-        let gas = gas as u64;
-
-        data_points.push((len as u64, inputs_and_outputs, average_ns, gas));
+        data_points.push(DataPoint {
+            family: String::new(),
+            scalar: len as u64,
+            vectors: select_vectors(inputs_and_outputs, params)
+        });
     }
 
     data_points
 }
 
-pub fn generate_ripemd_vectors(num_different_vectors: usize, num_tries_per_vector: usize) -> Vec<(u64, Vec<(Vec<u8>, [u8; 20])>, u128, u64)> {    
-    let limit = 256;
-    let step = 8;
-
-    let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+pub fn generate_ripemd_vectors(params: &RunParameters) -> Vec<DataPoint<(Vec<u8>, [u8; 20])>> {    
+    generate_ripemd_vectors_for_lengths(params, &params.lengths.lengths())
+}
 
+pub fn generate_ripemd_vectors_for_lengths(params: &RunParameters, lengths: &[usize]) -> Vec<DataPoint<(Vec<u8>, [u8; 20])>> {    
     let mut data_points = vec![];
 
-    for len in (0..=limit).step_by(step) {
-        let mut total = 0u128;
+    let mut rng = XorShiftRng::from_seed(params.rng_seed);
+
+    for len in lengths.iter().cloned() {
         let mut inputs_and_outputs = vec![];
-        for _ in 0..num_different_vectors {
+        for _ in 0..params.num_candidates() {
+            let seed = rng_state(&rng);
             let (input, output) = input_generators::generate_ripemd_vector_for_len(len, &mut rng);
 
             let input_clone = input.clone();
@@ -69,71 +502,118 @@ pub fn generate_ripemd_vectors(num_different_vectors: usize, num_tries_per_vecto
                 r == output
             };
 
-            let total_time = measurements::measure_with_validity(&runnable, &checker, num_tries_per_vector);
-            total += total_time;
-            inputs_and_outputs.push((input, output));
+            let samples = measurements::measure_samples_with_validity(&runnable, &checker, params.num_tries_per_vector, None);
+            inputs_and_outputs.push(MeasuredVector::new((input, output), TimingStats::from_samples(&samples), seed));
         }
 
-        let average_ns = total / (num_different_vectors as u128) / (num_tries_per_vector as u128);
-        let gas = average_ns * MGAS_PER_SECOND / 1_000_000_000;
-
-        let gas = gas as u64;
-
-        data_points.push((len as u64, inputs_and_outputs, average_ns, gas));
+        data_points.push(DataPoint {
+            family: String::new(),
+            scalar: len as u64,
+            vectors: select_vectors(inputs_and_outputs, params)
+        });
     }
 
     data_points
 }
 
-pub fn generate_blake2f_vectors(num_different_vectors: usize, num_tries_per_vector: usize) -> Vec<(u64, Vec<(Vec<u8>, [u8; 64])>, u128, u64)> {    
+pub fn generate_blake2f_vectors(params: &RunParameters) -> Vec<DataPoint<(Vec<u8>, [u8; 64])>> {    
+    use input_generators::Blake2fFamily;
+
     let num_rounds = vec![1, 2, 3, 4, 8, 16, 32, 64, 128];
 
-    let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+    let mut data_points = generate_blake2f_vectors_for_rounds(params, &num_rounds);
+
+    for family in Blake2fFamily::ALL.iter() {
+        let family_params = family.params().into_iter()
+            .filter(|p| *family == Blake2fFamily::InvalidLength || *p <= params.max_blake2f_rounds);
+        for param in family_params {
+            let num_tries = match family {
+                Blake2fFamily::InvalidLength => params.num_tries_per_vector,
+                _ => num_tries_for_blake2f_rounds(params.num_tries_per_vector, param)
+            };
+
+            let mut inputs_and_outputs = vec![];
+            for i in 0..params.num_candidates() {
+                let seed = derive_vector_seed(&params.rng_seed, family.name(), param, i);
+                let mut rng = XorShiftRng::from_seed(seed);
+                let (input, output) = input_generators::generate_blake2f_vector_for_family(*family, param, &mut rng);
+
+                let input_clone = input.clone();
+                let runnable = move || {
+                    runners::run_blake2f(&input_clone)
+                };
+
+                let checker = move |r: Result<[u8; 64], PrecompileError>| {
+                    r == output
+                };
+
+                let samples = measurements::measure_samples_with_validity(&runnable, &checker, num_tries, None);
+                let measured = MeasuredVector::new((input, output.unwrap_or([0u8; 64])), TimingStats::from_samples(&samples), seed);
+                inputs_and_outputs.push(match output {
+                    Ok(_) => measured,
+                    Err(e) => measured.with_expected_error(e)
+                });
+            }
+
+            data_points.push(DataPoint {
+                family: family.name().to_string(),
+                scalar: param,
+                vectors: select_vectors(inputs_and_outputs, params)
+            });
+        }
+    }
+
+    data_points
+}
 
+// Random vectors of the default family, more than 1024 rounds are measured fewer times
+pub fn generate_blake2f_vectors_for_rounds(params: &RunParameters, num_rounds: &[u64]) -> Vec<DataPoint<(Vec<u8>, [u8; 64])>> {    
     let mut data_points = vec![];
 
-    for rounds in num_rounds.into_iter() {
-        let mut total = 0u128;
+    let mut rng = XorShiftRng::from_seed(params.rng_seed);
+
+    for rounds in num_rounds.iter().cloned() {
+        let num_tries = num_tries_for_blake2f_rounds(params.num_tries_per_vector, rounds);
+
         let mut inputs_and_outputs = vec![];
-        for _ in 0..num_different_vectors {
-            let (input, output) = input_generators::generate_blake2f_vector_for_num_rounds(rounds, &mut rng);
+        for _ in 0..params.num_candidates() {
+            let seed = rng_state(&rng);
+            let (input, output) = input_generators::generate_blake2f_vector_for_num_rounds(rounds as usize, &mut rng);
 
             let input_clone = input.clone();
             let runnable = move || {
                 runners::run_blake2f(&input_clone)
             };
 
-            let checker = move |r: [u8; 64]| {
-                &r[..] == &output[..]
+            let checker = move |r: Result<[u8; 64], PrecompileError>| {
+                r == Ok(output)
             };
 
-            let total_time = measurements::measure_with_validity(&runnable, &checker, num_tries_per_vector);
-            total += total_time;
-            inputs_and_outputs.push((input, output));
+            let samples = measurements::measure_samples_with_validity(&runnable, &checker, num_tries, None);
+            inputs_and_outputs.push(MeasuredVector::new((input, output), TimingStats::from_samples(&samples), seed));
         }
 
-        let average_ns = total / (num_different_vectors as u128) / (num_tries_per_vector as u128);
-        let gas = average_ns * MGAS_PER_SECOND / 1_000_000_000;
-
-        let gas = gas as u64;
-
-        data_points.push((rounds as u64, inputs_and_outputs, average_ns, gas));
+        data_points.push(DataPoint {
+            family: String::new(),
+            scalar: rounds,
+            vectors: select_vectors(inputs_and_outputs, params)
+        });
     }
 
     data_points
 }
 
-pub fn generate_bn_add_vectors(num_different_vectors: usize, num_tries_per_vector: usize) -> Vec<(u64, Vec<([u8;128], [u8; 64])>, u128, u64)> {    
-    let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
-
+pub fn generate_bn_add_vectors(params: &RunParameters) -> Vec<DataPoint<([u8;128], [u8; 64])>> {    
     let mut data_points = vec![];
 
     let pb = make_pb();
-    pb.set_length(num_different_vectors as u64);
+    pb.set_length(params.num_candidates() as u64);
+
+    let mut rng = XorShiftRng::from_seed(params.rng_seed);
 
-    let mut total = 0u128;
     let mut inputs_and_outputs = vec![];
-    for _ in 0..num_different_vectors {
+    for _ in 0..params.num_candidates() {
+        let seed = rng_state(&rng);
         let (input, output) = input_generators::generate_bnadd_vector(&mut rng);
 
         let input_clone = input.clone();
@@ -141,39 +621,71 @@ pub fn generate_bn_add_vectors(num_different_vectors: usize, num_tries_per_vecto
             runners::run_bn_add(&input_clone)
         };
 
-        let checker = move |r: [u8; 64]| {
+        let checker = move |r: Result<[u8; 64], PrecompileError>| {
+            let r = r.expect("random points are valid");
             assert!(&r[..] != &[0u8; 64][..]);
             &r[..] == &output[..]
         };
 
-        let total_time = measurements::measure_with_validity(&runnable, &checker, num_tries_per_vector);
-        total += total_time;
-        inputs_and_outputs.push((input, output));
+        let samples = measurements::measure_samples_with_validity(&runnable, &checker, params.num_tries_per_vector, None);
+        inputs_and_outputs.push(MeasuredVector::new((input, output), TimingStats::from_samples(&samples), seed));
         pb.inc(1);
     }
 
-    let average_ns = total / (num_different_vectors as u128) / (num_tries_per_vector as u128);
-    let gas = average_ns * MGAS_PER_SECOND / 1_000_000_000;
+    data_points.push(DataPoint {
+        family: String::new(),
+        scalar: 0u64,
+        vectors: select_vectors(inputs_and_outputs, params)
+    });
+
+    for family in input_generators::BnAddFamily::ALL.iter() {
+        let mut inputs_and_outputs = vec![];
+        for i in 0..params.num_candidates() {
+            let seed = derive_vector_seed(&params.rng_seed, family.name(), 0u64, i);
+            let mut rng = XorShiftRng::from_seed(seed);
+            let (input, output) = input_generators::generate_bnadd_vector_for_family(*family, &mut rng);
+
+            let runnable = move || {
+                runners::run_bn_add(&input)
+            };
 
-    let gas = gas as u64;
+            let checker = move |r: Result<[u8; 64], PrecompileError>| {
+                r == output
+            };
 
-    data_points.push((0u64, inputs_and_outputs, average_ns, gas));
+            let samples = measurements::measure_samples_with_validity(&runnable, &checker, params.num_tries_per_vector, None);
+            let measured = MeasuredVector::new((input, output.unwrap_or([0u8; 64])), TimingStats::from_samples(&samples), seed);
+            inputs_and_outputs.push(match output {
+                Ok(_) => measured,
+                Err(e) => measured.with_expected_error(e)
+            });
+        }
+
+        data_points.push(DataPoint {
+            family: family.name().to_string(),
+            scalar: 0u64,
+            vectors: select_vectors(inputs_and_outputs, params)
+        });
+    }
 
     data_points
 }
 
 
-pub fn generate_bn_mul_vectors(num_different_vectors: usize, num_tries_per_vector: usize) -> Vec<(u64, Vec<([u8; 96], [u8; 64])>, u128, u64)> {    
-    let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+pub fn generate_bn_mul_vectors(params: &RunParameters) -> Vec<DataPoint<([u8; 96], [u8; 64])>> {    
+    use input_generators::BnMulFamily;
 
     let mut data_points = vec![];
 
+    let num_family_params: usize = BnMulFamily::ALL.iter().map(|f| f.params().len()).sum();
     let pb = make_pb();
-    pb.set_length(num_different_vectors as u64);
+    pb.set_length(((1 + num_family_params) * params.num_candidates()) as u64);
+
+    let mut rng = XorShiftRng::from_seed(params.rng_seed);
 
-    let mut total = 0u128;
     let mut inputs_and_outputs = vec![];
-    for _ in 0..num_different_vectors {
+    for _ in 0..params.num_candidates() {
+        let seed = rng_state(&rng);
         let (input, output) = input_generators::generate_bnmul_vector(&mut rng);
 
         let input_clone = input.clone();
@@ -181,41 +693,123 @@ pub fn generate_bn_mul_vectors(num_different_vectors: usize, num_tries_per_vecto
             runners::run_bn_mul(&input_clone)
         };
 
-        let checker = move |r: [u8; 64]| {
+        let checker = move |r: Result<[u8; 64], PrecompileError>| {
+            let r = r.expect("random points are valid");
             assert!(&r[..] != &[0u8; 64][..]);
             &r[..] == &output[..]
         };
 
-        let total_time = measurements::measure_with_validity(&runnable, &checker, num_tries_per_vector);
-        total += total_time;
-        inputs_and_outputs.push((input, output));
+        let samples = measurements::measure_samples_with_validity(&runnable, &checker, params.num_tries_per_vector, None);
+        inputs_and_outputs.push(MeasuredVector::new((input, output), TimingStats::from_samples(&samples), seed));
         pb.inc(1);
     }
 
-    let average_ns = total / (num_different_vectors as u128) / (num_tries_per_vector as u128);
-    let gas = average_ns * MGAS_PER_SECOND / 1_000_000_000;
-
-    let gas = gas as u64;
+    data_points.push(DataPoint {
+        family: String::new(),
+        scalar: 0u64,
+        vectors: select_vectors(inputs_and_outputs, params)
+    });
+
+    for family in BnMulFamily::ALL.iter() {
+        for param in family.params().into_iter() {
+            let mut inputs_and_outputs = vec![];
+            for i in 0..params.num_candidates() {
+                let seed = derive_vector_seed(&params.rng_seed, family.name(), param, i);
+                let mut rng = XorShiftRng::from_seed(seed);
+                let (input, output) = input_generators::generate_bnmul_vector_for_family(*family, param, &mut rng);
+
+                let runnable = move || {
+                    runners::run_bn_mul(&input)
+                };
+
+                let checker = move |r: Result<[u8; 64], PrecompileError>| {
+                    r == output
+                };
+
+                let samples = measurements::measure_samples_with_validity(&runnable, &checker, params.num_tries_per_vector, None);
+                let measured = MeasuredVector::new((input, output.unwrap_or([0u8; 64])), TimingStats::from_samples(&samples), seed);
+                inputs_and_outputs.push(match output {
+                    Ok(_) => measured,
+                    Err(e) => measured.with_expected_error(e)
+                });
+                pb.inc(1);
+            }
 
-    data_points.push((0u64, inputs_and_outputs, average_ns, gas));
+            data_points.push(DataPoint {
+                family: family.name().to_string(),
+                scalar: param,
+                vectors: select_vectors(inputs_and_outputs, params)
+            });
+        }
+    }
 
     data_points
 }
 
-pub fn generate_bnpair_vectors(num_different_vectors: usize, num_tries_per_vector: usize) -> Vec<(u64, Vec<(Vec<u8>, [u8; 32])>, u128, u64)> {    
+pub fn generate_bnpair_vectors(params: &RunParameters) -> Vec<DataPoint<(Vec<u8>, [u8; 32])>> {    
+    use input_generators::BnPairFamily;
+
     let num_pairs = vec![1, 2, 4, 8];
 
-    let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+    let pb = make_pb();
+    pb.set_length(((1 + BnPairFamily::ALL.len()) * num_pairs.len() * params.num_candidates()) as u64);
+
+    let mut data_points = generate_bnpair_data_points(params, &num_pairs, &pb);
+
+    for family in BnPairFamily::ALL.iter() {
+        for pairs in num_pairs.iter().cloned() {
+            let mut inputs_and_outputs = vec![];
+            for i in 0..params.num_candidates() {
+                let seed = derive_vector_seed(&params.rng_seed, family.name(), pairs as u64, i);
+                let mut rng = XorShiftRng::from_seed(seed);
+                let (input, output) = input_generators::generate_bnpair_vector_for_family(*family, pairs, &mut rng);
+
+                let input_clone = input.clone();
+                let runnable = move || {
+                    runners::run_bn_pair(&input_clone)
+                };
+
+                let checker = move |r: Result<[u8; 32], PrecompileError>| {
+                    r == output
+                };
+
+                let samples = measurements::measure_samples_with_validity(&runnable, &checker, params.num_tries_per_vector, None);
+                let measured = MeasuredVector::new((input, output.unwrap_or([0u8; 32])), TimingStats::from_samples(&samples), seed);
+                inputs_and_outputs.push(match output {
+                    Ok(_) => measured,
+                    Err(e) => measured.with_expected_error(e)
+                });
+                pb.inc(1);
+            }
 
-    let mut data_points = vec![];
+            data_points.push(DataPoint {
+                family: family.name().to_string(),
+                scalar: pairs as u64,
+                vectors: select_vectors(inputs_and_outputs, params)
+            });
+        }
+    }
 
+    data_points
+}
+
+pub fn generate_bnpair_vectors_for_pairs(params: &RunParameters, num_pairs: &[usize]) -> Vec<DataPoint<(Vec<u8>, [u8; 32])>> {    
     let pb = make_pb();
-    pb.set_length((num_pairs.len() * num_different_vectors) as u64);
+    pb.set_length((num_pairs.len() * params.num_candidates()) as u64);
+
+    generate_bnpair_data_points(params, num_pairs, &pb)
+}
+
+// Random vectors of the default family
+fn generate_bnpair_data_points(params: &RunParameters, num_pairs: &[usize], pb: &ProgressBar) -> Vec<DataPoint<(Vec<u8>, [u8; 32])>> {    
+    let mut data_points = vec![];
+
+    let mut rng = XorShiftRng::from_seed(params.rng_seed);
 
-    for pairs in num_pairs.into_iter() {
-        let mut total = 0u128;
+    for pairs in num_pairs.iter().cloned() {
         let mut inputs_and_outputs = vec![];
-        for _ in 0..num_different_vectors {
+        for _ in 0..params.num_candidates() {
+            let seed = rng_state(&rng);
             let (input, output) = input_generators::generate_bnpair_vector(pairs, &mut rng);
 
             let input_clone = input.clone();
@@ -223,176 +817,750 @@ pub fn generate_bnpair_vectors(num_different_vectors: usize, num_tries_per_vecto
                 runners::run_bn_pair(&input_clone)
             };
 
-            let checker = move |r: [u8; 32]| {
-                r == output
+            let checker = move |r: Result<[u8; 32], PrecompileError>| {
+                r == Ok(output)
             };
 
-            let total_time = measurements::measure_with_validity(&runnable, &checker, num_tries_per_vector);
-            total += total_time;
-            inputs_and_outputs.push((input, output));
+            let samples = measurements::measure_samples_with_validity(&runnable, &checker, params.num_tries_per_vector, None);
+            inputs_and_outputs.push(MeasuredVector::new((input, output), TimingStats::from_samples(&samples), seed));
             pb.inc(1);
         }
 
-        let average_ns = total / (num_different_vectors as u128) / (num_tries_per_vector as u128);
-        let gas = average_ns * MGAS_PER_SECOND / 1_000_000_000;
+        data_points.push(DataPoint {
+            family: String::new(),
+            scalar: pairs as u64,
+            vectors: select_vectors(inputs_and_outputs, params)
+        });
+    }
+
+    data_points
+}
+
+// Only malformed signatures, there is no default family of random vectors
+pub fn generate_ecrecover_vectors(params: &RunParameters) -> Vec<DataPoint<([u8; 128], [u8; 32])>> {    
+    let mut data_points = vec![];
+
+    for family in input_generators::EcrecoverFamily::ALL.iter() {
+        let mut inputs_and_outputs = vec![];
+        for i in 0..params.num_candidates() {
+            let seed = derive_vector_seed(&params.rng_seed, family.name(), 0u64, i);
+            let mut rng = XorShiftRng::from_seed(seed);
+            let (input, output) = input_generators::generate_ecrecover_vector_for_family(*family, &mut rng);
+
+            let runnable = move || {
+                runners::run_ecrecover(&input)
+            };
 
-        let gas = gas as u64;
+            let checker = move |r: [u8; 32]| {
+                r == output
+            };
 
-        data_points.push((pairs as u64, inputs_and_outputs, average_ns, gas));
+            let samples = measurements::measure_samples_with_validity(&runnable, &checker, params.num_tries_per_vector, None);
+            inputs_and_outputs.push(MeasuredVector::new((input, output), TimingStats::from_samples(&samples), seed));
+        }
+
+        data_points.push(DataPoint {
+            family: family.name().to_string(),
+            scalar: 0u64,
+            vectors: select_vectors(inputs_and_outputs, params)
+        });
     }
 
     data_points
 }
 
+// No address is recovered from a malformed signature and the precompile returns an empty output,
+// which the runner reports as zeros
+pub fn encode_ecrecover_output(output: &[u8; 32]) -> Vec<u8> {
+    if output == &[0u8; 32] {
+        vec![]
+    } else {
+        output.to_vec()
+    }
+}
+
+// Vectors of the same parameter are split into families of special cases, e.g. point doubling
+// for ECADD. Default family of random vectors has an empty name
+pub struct DataPoint<T> {
+    pub family: String,
+    pub scalar: u64,
+    pub vectors: Vec<MeasuredVector<T>>
+}
+
+pub struct MeasuredVector<T> {
+    pub vector: T,
+    pub timing: TimingStats,
+    // assigned by `select_vectors`
+    pub label: String,
+    // vector is generated from an RNG seeded with it, see `regenerate_vector`
+    pub seed: [u8; 16],
+    // invalid input the precompile must reject, the output of `vector` is meaningless then
+    pub expected_error: Option<PrecompileError>
+}
+
+impl<T> MeasuredVector<T> {
+    pub fn new(vector: T, timing: TimingStats, seed: [u8; 16]) -> Self {
+        Self {
+            vector,
+            timing,
+            label: String::new(),
+            seed,
+            expected_error: None
+        }
+    }
+
+    pub fn with_expected_error(mut self, error: PrecompileError) -> Self {
+        self.expected_error = Some(error);
+
+        self
+    }
+}
+
+impl<T> DataPoint<T> {
+    pub fn average_ns(&self) -> u128 {
+        if self.vectors.is_empty() {
+            return 0;
+        }
+        let total: u128 = self.vectors.iter().map(|v| v.timing.mean_ns).sum();
+
+        total / (self.vectors.len() as u128)
+    }
+}
+
+pub fn gas_for_ns(ns: u128, gas_per_second: u128) -> u64 {
+    (ns * gas_per_second / 1_000_000_000) as u64
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn perform_measurements<
     T, 
-    F: Fn() -> Vec<(u64, Vec<T>, u128, u64)>, 
+    F: Fn() -> Vec<DataPoint<T>>, 
     C: Fn(T) -> (Vec<u8>, Vec<u8>),
     A: Fn(u64) -> String
 >(
     should_write: bool,
     current_pricer: crate::pricers::Pricer,
     proposed_pricer: crate::pricers::Pricer,
+    gas_per_second: u128,
     runner: F,
     transformer: C,
     writers: Vec<Box<dyn BenchmarkDataWriter>>,
     ann: A
-) {
+) -> Result<(), FailedFlushes> {
     let data = runner();
-    let mut writers = writers;
-    for (scalar_param, ins_and_outs, _, gas) in data.into_iter() {
-        let data_as_vector: Vec<_> = ins_and_outs.into_iter().map(|el| transformer(el)).collect();
+    let mut writers: Vec<RetryingWriter> = writers.into_iter().map(RetryingWriter::new).collect();
+    for data_point in data.into_iter() {
+        let scalar_param = data_point.scalar;
+        let gas = gas_for_ns(data_point.average_ns(), gas_per_second);
         let current_gas = current_pricer.price(scalar_param);
         let proposed_gas = proposed_pricer.price(scalar_param);
         if should_write {
-            for writer in writers.iter_mut() {
-                for v in data_as_vector.clone().into_iter() {
-                    writer.add_per_scalar_input(scalar_param, v, current_gas, proposed_gas);
+            for measured in data_point.vectors.into_iter() {
+                let timing = measured.timing;
+                let (input, output) = transformer(measured.vector);
+                let record = VectorRecord {
+                    family: &data_point.family,
+                    scalar: scalar_param,
+                    label: &measured.label,
+                    seed: measured.seed,
+                    input: &input,
+                    output: &output,
+                    expected_error: measured.expected_error,
+                    timing: &timing,
+                    gas: gas_for_ns(timing.mean_ns, gas_per_second),
+                    current_gas,
+                    proposed_gas
+                };
+                for writer in writers.iter_mut() {
+                    writer.add(&record);
                 }
             }
         }
         let annotation = ann(scalar_param);
 
+        if !data_point.family.is_empty() {
+            println!("Family {}:", data_point.family);
+        }
         println!("{}", annotation);
         print_gases(gas, current_gas, proposed_gas);
     }
 
-    if should_write {
-        for writer in writers.into_iter() {
-            writer.flush();
+    if !should_write {
+        return Ok(());
+    }
+
+    let failures: Vec<_> = writers.into_iter().filter_map(|w| w.flush().err()).collect();
+    if !failures.is_empty() {
+        return Err(save_into_fallback_path(failures));
+    }
+
+    Ok(())
+}
+
+// Records a writer failed to add are kept and retried before every later record and before
+// the flush, so an output error never drops measurements. If some still can't be added, the
+// error is reported when the writer is flushed
+struct RetryingWriter {
+    writer: Box<dyn BenchmarkDataWriter>,
+    pending: std::collections::VecDeque<OwnedVectorRecord>,
+    error: Option<std::io::Error>
+}
+
+impl RetryingWriter {
+    fn new(writer: Box<dyn BenchmarkDataWriter>) -> Self {
+        Self {
+            writer,
+            pending: std::collections::VecDeque::new(),
+            error: None
         }
     }
+
+    fn add(&mut self, record: &VectorRecord) {
+        // records are added in order, so a new one waits behind those that failed
+        if self.retry_pending() {
+            if let Err(e) = self.writer.add_per_scalar_input(record) {
+                self.error = Some(e);
+                self.pending.push_back(OwnedVectorRecord::from_record(record));
+            }
+        } else {
+            self.pending.push_back(OwnedVectorRecord::from_record(record));
+        }
+    }
+
+    // Returns `true` if no record is pending anymore
+    fn retry_pending(&mut self) -> bool {
+        while let Some(pending) = self.pending.front() {
+            if let Err(e) = self.writer.add_per_scalar_input(&pending.as_record()) {
+                self.error = Some(e);
+                return false;
+            }
+            self.pending.pop_front();
+        }
+
+        true
+    }
+
+    fn flush(mut self) -> Result<(), (Box<dyn BenchmarkDataWriter>, std::io::Error)> {
+        if !self.retry_pending() {
+            let e = self.error.take().expect("error of a pending record is kept");
+            let e = std::io::Error::new(e.kind(), format!("{} vector(s) could not be added: {}", self.pending.len(), e));
+
+            return Err((self.writer, e));
+        }
+
+        match self.writer.flush() {
+            Ok(()) => Ok(()),
+            Err(e) => Err((self.writer, e))
+        }
+    }
+}
+
+struct OwnedVectorRecord {
+    family: String,
+    scalar: u64,
+    label: String,
+    seed: [u8; 16],
+    input: Vec<u8>,
+    output: Vec<u8>,
+    expected_error: Option<PrecompileError>,
+    timing: TimingStats,
+    gas: u64,
+    current_gas: u64,
+    proposed_gas: u64
 }
 
+impl OwnedVectorRecord {
+    fn from_record(record: &VectorRecord) -> Self {
+        Self {
+            family: record.family.to_string(),
+            scalar: record.scalar,
+            label: record.label.to_string(),
+            seed: record.seed,
+            input: record.input.to_vec(),
+            output: record.output.to_vec(),
+            expected_error: record.expected_error,
+            timing: record.timing.clone(),
+            gas: record.gas,
+            current_gas: record.current_gas,
+            proposed_gas: record.proposed_gas
+        }
+    }
+
+    fn as_record(&self) -> VectorRecord<'_> {
+        VectorRecord {
+            family: &self.family,
+            scalar: self.scalar,
+            label: &self.label,
+            seed: self.seed,
+            input: &self.input,
+            output: &self.output,
+            expected_error: self.expected_error,
+            timing: &self.timing,
+            gas: self.gas,
+            current_gas: self.current_gas,
+            proposed_gas: self.proposed_gas
+        }
+    }
+}
+
+// Single measured vector, `gas` is derived from the vector's mean running time
+pub struct VectorRecord<'a> {
+    pub family: &'a str,
+    pub scalar: u64,
+    pub label: &'a str,
+    pub seed: [u8; 16],
+    pub input: &'a [u8],
+    pub output: &'a [u8],
+    // vectors with an expected error are only written by `FailJSONWriter` and `TimingsWriter`
+    pub expected_error: Option<PrecompileError>,
+    pub timing: &'a TimingStats,
+    pub gas: u64,
+    pub current_gas: u64,
+    pub proposed_gas: u64
+}
+
+// Writers receive records one by one and should not keep them in memory
 pub trait BenchmarkDataWriter: 'static {
-    fn add_per_scalar_input(&mut self, scalar: u64, ins_and_outs: (Vec<u8>, Vec<u8>), current_gas: u64, proposed_gas: u64);
-    fn flush(&self);
+    fn add_per_scalar_input(&mut self, record: &VectorRecord) -> std::io::Result<()>;
+    // flushing never consumes accumulated data, so it can be repeated after a failure
+    fn flush(&mut self) -> std::io::Result<()>;
+    fn set_base_path(&mut self, base_path: &str);
+}
+
+// Writers that failed to flush still hold all the accumulated measurements
+pub struct FailedFlushes {
+    pub writers: Vec<Box<dyn BenchmarkDataWriter>>,
+    pub errors: Vec<std::io::Error>,
+    pub fallback_path: Option<String>
 }
+
+impl FailedFlushes {
+    pub fn retry(self) -> Result<(), FailedFlushes> {
+        let mut writers = vec![];
+        let mut errors = vec![];
+        for mut writer in self.writers.into_iter() {
+            if let Err(e) = writer.flush() {
+                writers.push(writer);
+                errors.push(e);
+            }
+        }
+
+        if writers.is_empty() {
+            Ok(())
+        } else {
+            Err(FailedFlushes {
+                writers,
+                errors,
+                fallback_path: self.fallback_path
+            })
+        }
+    }
+}
+
+impl std::fmt::Display for FailedFlushes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} writer(s) failed to flush:", self.errors.len())?;
+        for e in self.errors.iter() {
+            write!(f, " {};", e)?;
+        }
+        match &self.fallback_path {
+            Some(path) => write!(f, " measurements were saved into {}", path),
+            None => write!(f, " measurements are only kept in memory")
+        }
+    }
+}
+
+impl std::fmt::Debug for FailedFlushes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+fn fallback_output_path() -> String {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    std::env::temp_dir()
+        .join(format!("bench_precompiles_{}_{}", std::process::id(), timestamp))
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn save_into_fallback_path(failures: Vec<(Box<dyn BenchmarkDataWriter>, std::io::Error)>) -> FailedFlushes {
+    use colored::*;
+
+    let fallback_path = fallback_output_path();
+    let mut saved = true;
+    let mut writers = vec![];
+    let mut errors = vec![];
+    for (mut writer, e) in failures.into_iter() {
+        println!("{}", format!("Failed to write vectors: {}", e).red());
+        writer.set_base_path(&fallback_path);
+        if let Err(fallback_e) = writer.flush() {
+            println!("{}", format!("Failed to write vectors into fallback path {}: {}", fallback_path, fallback_e).red());
+            saved = false;
+        }
+        writers.push(writer);
+        errors.push(e);
+    }
+
+    if saved {
+        println!("{}", format!("Vectors were saved into {}", fallback_path).yellow());
+    }
+
+    FailedFlushes {
+        writers,
+        errors,
+        fallback_path: if saved { Some(fallback_path) } else { None }
+    }
+}
+
 pub struct CSVWriter {
     base_path: String,
-    accumulated_data_points: std::collections::HashMap<(u64, u64, u64), Vec<(Vec<u8>, Vec<u8>)>>
+    spool: SharedSpool
 }
 
 impl CSVWriter {
-    pub fn new_for_path(base_path: &str) -> Self {
+    pub fn new_for_path(base_path: &str, spool: &SharedSpool) -> Self {
         Self {
             base_path: base_path.to_string(),
-            accumulated_data_points: std::collections::HashMap::new()
+            spool: spool.clone()
         }
     }
 }
 
+// Vectors of the default family are written as `{schedule}/input_param_scalar_{scalar}_gas_{gas}.csv`,
+// of other families as `{schedule}/{family}/input_param_scalar_{scalar}_gas_{gas}.csv`
 impl BenchmarkDataWriter for CSVWriter {
-    fn add_per_scalar_input(&mut self, scalar: u64, ins_and_outs: (Vec<u8>, Vec<u8>), current_gas: u64, proposed_gas: u64) {
-        let key = (scalar, current_gas, proposed_gas);
-        let entry = self.accumulated_data_points.entry(key).or_insert(vec![]);
-        entry.push(ins_and_outs);
+    fn add_per_scalar_input(&mut self, record: &VectorRecord) -> std::io::Result<()> {
+        if record.expected_error.is_some() {
+            return Ok(());
+        }
+        self.spool.borrow_mut().add(spool_key(record), record.input, record.output, record.label, &record.seed)?;
+
+        Ok(())
     }
 
-    fn flush(&self) {
-        let mut keys: Vec<_> = self.accumulated_data_points.keys().collect();
-        keys.sort_by(|a, b| {
-            a.0.cmp(&b.0)
-        });
-        for key in keys.into_iter() {
-            let data = self.accumulated_data_points.get(&key).unwrap().clone();
-            let (scalar, current_gas, proposed_gas) = key;
-            for (p, g) in vec!["current", "proposed"].into_iter().zip(vec![current_gas, proposed_gas].into_iter()) {
-                let file = std::fs::File::create(&format!("{}/{}/input_param_scalar_{}_gas_{}.csv", &self.base_path, p, scalar, g)).unwrap();
-                let mut writer = csv::Writer::from_writer(file);
-                let mut dedup_set = std::collections::HashSet::new();
-                for (input, output) in data.clone().into_iter() {
-                    if !dedup_set.contains(&input) {
-                        dedup_set.insert(input.clone());
-                        writer.write_record(&[
-                            hex::encode(&input),
-                            hex::encode(&output)
-                        ]).unwrap();
-                    }
-                }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.spool.borrow_mut().flush()?;
+        let spool = self.spool.borrow();
+        for key in spool.keys().iter() {
+            let (family, scalar, current_gas, proposed_gas) = key;
+            for (p, g) in ["current", "proposed"].iter().zip([current_gas, proposed_gas].iter()) {
+                let dir = if family.is_empty() {
+                    format!("{}/{}", &self.base_path, p)
+                } else {
+                    format!("{}/{}/{}", &self.base_path, p, family)
+                };
+                let path = format!("{}/input_param_scalar_{}_gas_{}.csv", dir, scalar, g);
+                helpers::write_atomically(&path, |file| {
+                    let mut writer = csv::Writer::from_writer(file);
+                    spool.for_each_record(key, |input, output, _, _| {
+                        writer.write_record(&[hex::encode(input), hex::encode(output)])?;
+
+                        Ok(())
+                    })?;
+
+                    writer.flush()
+                })?;
             }
         }
+
+        Ok(())
+    }
+
+    fn set_base_path(&mut self, base_path: &str) {
+        self.base_path = base_path.to_string();
     }
 }
 
 pub struct JSONWriter {
     base_path: String,
     test_name: String,
-    accumulated_data_points: std::collections::HashMap<(u64, u64, u64), Vec<(Vec<u8>, Vec<u8>)>>
+    spool: SharedSpool
 }
 
 impl JSONWriter {
-    pub fn new_for_path_and_name(base_path: &str, test_name: &str) -> Self {
+    pub fn new_for_path_and_name(base_path: &str, test_name: &str, spool: &SharedSpool) -> Self {
         Self {
             base_path: base_path.to_string(),
             test_name: test_name.to_string(),
-            accumulated_data_points: std::collections::HashMap::new()
+            spool: spool.clone()
         }
     }
 }
 
 impl BenchmarkDataWriter for JSONWriter {
-    fn add_per_scalar_input(&mut self, scalar: u64, ins_and_outs: (Vec<u8>, Vec<u8>), current_gas: u64, proposed_gas: u64) {
-        let key = (scalar, current_gas, proposed_gas);
-        let entry = self.accumulated_data_points.entry(key).or_insert(vec![]);
-        entry.push(ins_and_outs);
-    }
-
-    fn flush(&self) {
-        let file = std::fs::File::create(&format!("{}/common_{}.json", self.base_path, self.test_name)).unwrap();
-        let mut test_vectors = vec![];
-        let mut keys: Vec<_> = self.accumulated_data_points.keys().collect();
-        keys.sort_by(|a, b| {
-            a.0.cmp(&b.0)
-        });
-        for key in keys.into_iter() {
-            let data = self.accumulated_data_points.get(&key).unwrap().clone();
-            let (scalar, _current_gas, _proposed_gas) = key;
-            let mut dedup_set = std::collections::HashSet::new();
-            let mut i = 0;
-            for (input, output) in data.clone().into_iter() {
-                if !dedup_set.contains(&input) {
-                    dedup_set.insert(input.clone());
-                    let testname = format!("{}_{}_{}", self.test_name, scalar, i);
-                    let record = serialization::GethJsonFormat::new_from_data_and_name(&input, &output, testname);
-                    test_vectors.push(record);
-                    i += 1;
-                }
+    fn add_per_scalar_input(&mut self, record: &VectorRecord) -> std::io::Result<()> {
+        if record.expected_error.is_some() {
+            return Ok(());
+        }
+        self.spool.borrow_mut().add(spool_key(record), record.input, record.output, record.label, &record.seed)?;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        use std::io::Write;
+
+        self.spool.borrow_mut().flush()?;
+        let spool = self.spool.borrow();
+
+        let path = format!("{}/common_{}.json", self.base_path, self.test_name);
+        helpers::write_atomically(&path, |file| {
+            write!(file, "[")?;
+            let mut first = true;
+            for key in spool.keys().iter() {
+                let (family, scalar, _current_gas, _proposed_gas) = key;
+                spool.for_each_record(key, |input, output, label, seed| {
+                    if !first {
+                        write!(file, ",")?;
+                    }
+                    first = false;
+                    let testname = vector_name(&self.test_name, family, *scalar, label);
+                    let record = serialization::GethJsonFormat::new_from_data_and_name(input, output, testname).with_seed(seed);
+                    to_writer(&mut *file, &record)?;
+
+                    Ok(())
+                })?;
             }
+            write!(file, "]")?;
+
+            Ok(())
+        })
+    }
+
+    fn set_base_path(&mut self, base_path: &str) {
+        self.base_path = base_path.to_string();
+    }
+}
+
+pub struct StateTestWriter {
+    base_path: String,
+    test_name: String,
+    precompile_address: u8,
+    spool: SharedSpool
+}
+
+impl StateTestWriter {
+    pub fn new_for_path_name_and_address(base_path: &str, test_name: &str, precompile_address: u8, spool: &SharedSpool) -> Self {
+        Self {
+            base_path: base_path.to_string(),
+            test_name: test_name.to_string(),
+            precompile_address,
+            spool: spool.clone()
         }
-        
-        to_writer(file, &test_vectors).unwrap();
     }
 }
 
-pub fn make_csv_writer_for_path(base_path: &str) -> Box<dyn BenchmarkDataWriter> {
-    let writer = CSVWriter::new_for_path(base_path);
+impl BenchmarkDataWriter for StateTestWriter {
+    fn add_per_scalar_input(&mut self, record: &VectorRecord) -> std::io::Result<()> {
+        if record.expected_error.is_some() {
+            return Ok(());
+        }
+        self.spool.borrow_mut().add(spool_key(record), record.input, record.output, record.label, &record.seed)?;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.spool.borrow_mut().flush()?;
+        let spool = self.spool.borrow();
+
+        let keys = spool.keys();
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        for p in ["current", "proposed"].iter() {
+            let network = match state_test::network_for_schedule(p) {
+                Some(network) => network,
+                None => continue
+            };
+            let visit_entries = |f: &mut state_test::EntryVisitor| {
+                for key in keys.iter() {
+                    let (family, scalar, current_gas, proposed_gas) = key;
+                    let gas = if *p == "current" { *current_gas } else { *proposed_gas };
+                    spool.for_each_record(key, |input, output, label, _| {
+                        let label = vector_name(&self.test_name, family, *scalar, label);
+
+                        f(&label, gas, input, output)
+                    })?;
+                }
+
+                Ok(())
+            };
+
+            let name = format!("{}_{}", self.test_name, p);
+            let path = format!("{}/{}Filler.json", self.base_path, name);
+            helpers::write_atomically(&path, |file| {
+                state_test::write_filler(file, &name, &network, self.precompile_address, visit_entries)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn set_base_path(&mut self, base_path: &str) {
+        self.base_path = base_path.to_string();
+    }
+}
+
+// Per-vector timings are small, so unlike vectors they are kept in memory until flush
+pub struct TimingsWriter {
+    base_path: String,
+    test_name: String,
+    gas_per_second: u128,
+    records: Vec<results::TimingRecord>
+}
+
+impl TimingsWriter {
+    pub fn new_for_path_and_name(base_path: &str, test_name: &str, gas_per_second: u128) -> Self {
+        Self {
+            base_path: base_path.to_string(),
+            test_name: test_name.to_string(),
+            gas_per_second,
+            records: vec![]
+        }
+    }
+}
+
+impl BenchmarkDataWriter for TimingsWriter {
+    fn add_per_scalar_input(&mut self, record: &VectorRecord) -> std::io::Result<()> {
+        let mut schedule_gas = std::collections::BTreeMap::new();
+        schedule_gas.insert(String::from("current"), record.current_gas);
+        schedule_gas.insert(String::from("proposed"), record.proposed_gas);
+
+        self.records.push(results::TimingRecord {
+            precompile: self.test_name.clone(),
+            family: record.family.to_string(),
+            scalar: record.scalar,
+            name: vector_name(&self.test_name, record.family, record.scalar, record.label),
+            label: record.label.to_string(),
+            expected_error: record.expected_error.map(|e| e.message().to_string()),
+            seed: hex::encode(record.seed),
+            samples: record.timing.samples,
+            median_ns: record.timing.median_ns,
+            mean_ns: record.timing.mean_ns,
+            stddev_ns: record.timing.stddev_ns,
+            gas: record.gas,
+            schedule_gas
+        });
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let measurement_results = results::MeasurementResults {
+            precompile: self.test_name.clone(),
+            gas_per_second: self.gas_per_second,
+            environment: environment::current_environment(),
+            records: self.records.clone()
+        };
+
+        results::write_results(&self.base_path, &measurement_results)
+    }
+
+    fn set_base_path(&mut self, base_path: &str) {
+        self.base_path = base_path.to_string();
+    }
+}
+
+// Invalid vectors in the format of go-ethereum precompile failure tests, written as
+// `fail-{test_name}.json`. There are few of them, so they are kept in memory until flush
+pub struct FailJSONWriter {
+    base_path: String,
+    test_name: String,
+    records: Vec<serialization::GethFailJsonFormat>,
+    seen_inputs: std::collections::HashSet<Vec<u8>>
+}
+
+impl FailJSONWriter {
+    pub fn new_for_path_and_name(base_path: &str, test_name: &str) -> Self {
+        Self {
+            base_path: base_path.to_string(),
+            test_name: test_name.to_string(),
+            records: vec![],
+            seen_inputs: std::collections::HashSet::new()
+        }
+    }
+}
+
+impl BenchmarkDataWriter for FailJSONWriter {
+    fn add_per_scalar_input(&mut self, record: &VectorRecord) -> std::io::Result<()> {
+        let error = match record.expected_error {
+            Some(error) => error,
+            None => return Ok(())
+        };
+        if !self.seen_inputs.insert(record.input.to_vec()) {
+            return Ok(());
+        }
+
+        let name = vector_name(&self.test_name, record.family, record.scalar, record.label);
+        self.records.push(serialization::GethFailJsonFormat::new_from_data_and_name(record.input, error.geth_error(), name).with_seed(&record.seed));
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.records.is_empty() {
+            return Ok(());
+        }
+
+        let path = format!("{}/fail-{}.json", self.base_path, self.test_name);
+        helpers::write_atomically(&path, |file| {
+            to_writer(file, &self.records)?;
+
+            Ok(())
+        })
+    }
+
+    fn set_base_path(&mut self, base_path: &str) {
+        self.base_path = base_path.to_string();
+    }
+}
+
+fn spool_key(record: &VectorRecord) -> crate::spool::SpoolKey {
+    (record.family.to_string(), record.scalar, record.current_gas, record.proposed_gas)
+}
+
+// Test name of a vector in JSON files and state tests, the default family is omitted
+fn vector_name(test_name: &str, family: &str, scalar: u64, label: &str) -> String {
+    if family.is_empty() {
+        format!("{}_{}_{}", test_name, scalar, label)
+    } else {
+        format!("{}_{}_{}_{}", test_name, family, scalar, label)
+    }
+}
+
+pub fn make_csv_writer_for_path(base_path: &str, spool: &SharedSpool) -> Box<dyn BenchmarkDataWriter> {
+    let writer = CSVWriter::new_for_path(base_path, spool);
+
+    box_writer(writer)
+}
+
+pub fn make_json_writer_for_path_and_test_name(base_path: &str, test_name: &str, spool: &SharedSpool) -> Box<dyn BenchmarkDataWriter> {
+    let writer = JSONWriter::new_for_path_and_name(base_path, test_name, spool);
+
+    box_writer(writer)
+}
+
+pub fn make_state_test_writer_for_path_name_and_address(base_path: &str, test_name: &str, precompile_address: u8, spool: &SharedSpool) -> Box<dyn BenchmarkDataWriter> {
+    let writer = StateTestWriter::new_for_path_name_and_address(base_path, test_name, precompile_address, spool);
 
     box_writer(writer)
 }
 
-pub fn make_json_writer_for_path_and_test_name(base_path: &str, test_name: &str) -> Box<dyn BenchmarkDataWriter> {
-    let writer = JSONWriter::new_for_path_and_name(base_path, test_name);
+pub fn make_fail_json_writer_for_path_and_test_name(base_path: &str, test_name: &str) -> Box<dyn BenchmarkDataWriter> {
+    let writer = FailJSONWriter::new_for_path_and_name(base_path, test_name);
+
+    box_writer(writer)
+}
+
+pub fn make_timings_writer_for_path_and_test_name(base_path: &str, test_name: &str, gas_per_second: u128) -> Box<dyn BenchmarkDataWriter> {
+    let writer = TimingsWriter::new_for_path_and_name(base_path, test_name, gas_per_second);
 
     box_writer(writer)
 }
@@ -474,13 +1642,17 @@ pub fn box_writer(writer: impl BenchmarkDataWriter) -> Box<dyn BenchmarkDataWrit
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::spool::RecordSpool;
 
     fn do_sha256(write: bool) {
         let base_path = "./vectors/sha256";
         let test_name = "sha256";
 
+        let params = RunParameters::from_env(10, 10000);
+        let run_started = std::time::SystemTime::now();
+
         let data_fn = || {
-            generate_sha256_vectors(10, 10000)
+            generate_sha256_vectors(&params)
         };
 
         let transformer_fn = |a: (Vec<u8>, [u8; 32])| {
@@ -493,26 +1665,40 @@ mod test {
             format!("For length {}:", len)
         };
         
-        let csv_writer_fn = make_csv_writer_for_path(base_path);
-        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name);
+        let spool = RecordSpool::shared(test_name);
+        
+        let csv_writer_fn = make_csv_writer_for_path(base_path, &spool);
+        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name, &spool);
+        let timings_writer_fn = make_timings_writer_for_path_and_test_name(base_path, test_name, params.gas_rate.gas_per_second);
+        let state_test_writer_fn = make_state_test_writer_for_path_name_and_address(base_path, test_name, 0x02, &spool);
 
         perform_measurements(
             write,
             crate::pricers::current_sha256_pricer(),
             crate::pricers::proposed_sha256_pricer(),
+            params.gas_rate.gas_per_second,
             data_fn,
             transformer_fn,
-            vec![csv_writer_fn, json_writer_fn],
+            vec![csv_writer_fn, json_writer_fn, state_test_writer_fn, timings_writer_fn],
             ann_fn
-        );
+        ).expect("vectors must be written");
+
+        if write {
+            let fit = crate::fitting::fit_and_write(base_path).expect("fit must be written");
+            println!("{}", crate::fitting::describe_fit(&fit));
+            crate::manifest::write_manifest(base_path, test_name, &params, &["current", "proposed"], run_started).expect("manifest must be written");
+        }
     }
 
     fn do_ripemd(write: bool) {
         let base_path = "./vectors/ripemd";
         let test_name = "ripemd";
 
+        let params = RunParameters::from_env(10, 10000);
+        let run_started = std::time::SystemTime::now();
+
         let data_fn = || {
-            generate_ripemd_vectors(10, 10000)
+            generate_ripemd_vectors(&params)
         };
 
         let transformer_fn = |a: (Vec<u8>, [u8; 20])| {
@@ -528,26 +1714,40 @@ mod test {
             format!("For length {}:", len)
         };
 
-        let csv_writer_fn = make_csv_writer_for_path(base_path);
-        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name);
+        let spool = RecordSpool::shared(test_name);
+
+        let csv_writer_fn = make_csv_writer_for_path(base_path, &spool);
+        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name, &spool);
+        let timings_writer_fn = make_timings_writer_for_path_and_test_name(base_path, test_name, params.gas_rate.gas_per_second);
+        let state_test_writer_fn = make_state_test_writer_for_path_name_and_address(base_path, test_name, 0x03, &spool);
 
         perform_measurements(
             write,
             crate::pricers::current_ripemd_pricer(),
             crate::pricers::proposed_ripemd_pricer(),
+            params.gas_rate.gas_per_second,
             data_fn,
             transformer_fn,
-            vec![csv_writer_fn, json_writer_fn],
+            vec![csv_writer_fn, json_writer_fn, state_test_writer_fn, timings_writer_fn],
             ann_fn
-        );
+        ).expect("vectors must be written");
+
+        if write {
+            let fit = crate::fitting::fit_and_write(base_path).expect("fit must be written");
+            println!("{}", crate::fitting::describe_fit(&fit));
+            crate::manifest::write_manifest(base_path, test_name, &params, &["current", "proposed"], run_started).expect("manifest must be written");
+        }
     }
 
     fn do_blake2f(write: bool) {
         let base_path = "./vectors/blake2f";
         let test_name = "blake2f";
 
+        let params = RunParameters::from_env(10, 10000);
+        let run_started = std::time::SystemTime::now();
+
         let data_fn = || {
-            generate_blake2f_vectors(10, 10000)
+            generate_blake2f_vectors(&params)
         };
 
         let transformer_fn = |a: (Vec<u8>, [u8; 64])| {
@@ -556,30 +1756,45 @@ mod test {
             (a, b.to_vec())
         };
 
-        let ann_fn = |rounds: u64| {
-            format!("For {} rounds:", rounds)
+        let ann_fn = |param: u64| {
+            format!("For parameter {}:", param)
         };
         
-        let csv_writer_fn = make_csv_writer_for_path(base_path);
-        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name);
+        let spool = RecordSpool::shared(test_name);
+        
+        let csv_writer_fn = make_csv_writer_for_path(base_path, &spool);
+        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name, &spool);
+        let timings_writer_fn = make_timings_writer_for_path_and_test_name(base_path, test_name, params.gas_rate.gas_per_second);
+        let state_test_writer_fn = make_state_test_writer_for_path_name_and_address(base_path, test_name, 0x09, &spool);
+        let fail_json_writer_fn = make_fail_json_writer_for_path_and_test_name(base_path, test_name);
         
         perform_measurements(
             write,
             crate::pricers::blake2f_pricer(),
             crate::pricers::blake2f_pricer(),
+            params.gas_rate.gas_per_second,
             data_fn,
             transformer_fn,
-            vec![csv_writer_fn, json_writer_fn],
+            vec![csv_writer_fn, json_writer_fn, state_test_writer_fn, fail_json_writer_fn, timings_writer_fn],
             ann_fn
-        );
+        ).expect("vectors must be written");
+
+        if write {
+            let fit = crate::fitting::fit_and_write(base_path).expect("fit must be written");
+            println!("{}", crate::fitting::describe_fit(&fit));
+            crate::manifest::write_manifest(base_path, test_name, &params, &["current", "proposed"], run_started).expect("manifest must be written");
+        }
     }
 
     fn do_bnadd(write: bool) {
         let base_path = "./vectors/bnadd";
         let test_name = "bnadd";
 
+        let params = RunParameters::from_env(10, 10000);
+        let run_started = std::time::SystemTime::now();
+
         let data_fn = || {
-            generate_bn_add_vectors(10, 10000)
+            generate_bn_add_vectors(&params)
         };
 
         let transformer_fn = |a: ([u8; 128], [u8; 64])| {
@@ -592,26 +1807,39 @@ mod test {
             String::from("")
         };
 
-        let csv_writer_fn = make_csv_writer_for_path(base_path);
-        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name);
+        let spool = RecordSpool::shared(test_name);
+
+        let csv_writer_fn = make_csv_writer_for_path(base_path, &spool);
+        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name, &spool);
+        let timings_writer_fn = make_timings_writer_for_path_and_test_name(base_path, test_name, params.gas_rate.gas_per_second);
+        let state_test_writer_fn = make_state_test_writer_for_path_name_and_address(base_path, test_name, 0x06, &spool);
+        let fail_json_writer_fn = make_fail_json_writer_for_path_and_test_name(base_path, test_name);
         
         perform_measurements(
             write,
             crate::pricers::current_bnadd_pricer(),
             crate::pricers::proposed_bnadd_pricer(),
+            params.gas_rate.gas_per_second,
             data_fn,
             transformer_fn,
-            vec![csv_writer_fn, json_writer_fn],
+            vec![csv_writer_fn, json_writer_fn, state_test_writer_fn, fail_json_writer_fn, timings_writer_fn],
             ann_fn
-        );
+        ).expect("vectors must be written");
+
+        if write {
+            crate::manifest::write_manifest(base_path, test_name, &params, &["current", "proposed"], run_started).expect("manifest must be written");
+        }
     }
 
     fn do_bnmul(write: bool) {
         let base_path = "./vectors/bnmul";
         let test_name = "bnmul";
 
+        let params = RunParameters::from_env(10, 10000);
+        let run_started = std::time::SystemTime::now();
+
         let data_fn = || {
-            generate_bn_mul_vectors(10, 10000)
+            generate_bn_mul_vectors(&params)
         };
 
         let transformer_fn = |a: ([u8; 96], [u8; 64])| {
@@ -620,22 +1848,32 @@ mod test {
             (a.to_vec(), b.to_vec())
         };
 
-        let ann_fn = |_: u64| {
-            String::from("")
+        let ann_fn = |param: u64| {
+            format!("For parameter {}:", param)
         };
 
-        let csv_writer_fn = make_csv_writer_for_path(base_path);
-        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name);
+        let spool = RecordSpool::shared(test_name);
+
+        let csv_writer_fn = make_csv_writer_for_path(base_path, &spool);
+        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name, &spool);
+        let timings_writer_fn = make_timings_writer_for_path_and_test_name(base_path, test_name, params.gas_rate.gas_per_second);
+        let state_test_writer_fn = make_state_test_writer_for_path_name_and_address(base_path, test_name, 0x07, &spool);
+        let fail_json_writer_fn = make_fail_json_writer_for_path_and_test_name(base_path, test_name);
         
         perform_measurements(
             write,
             crate::pricers::current_bnmul_pricer(),
             crate::pricers::proposed_bnmul_pricer(),
+            params.gas_rate.gas_per_second,
             data_fn,
             transformer_fn,
-            vec![csv_writer_fn, json_writer_fn],
+            vec![csv_writer_fn, json_writer_fn, state_test_writer_fn, fail_json_writer_fn, timings_writer_fn],
             ann_fn
-        );
+        ).expect("vectors must be written");
+
+        if write {
+            crate::manifest::write_manifest(base_path, test_name, &params, &["current", "proposed"], run_started).expect("manifest must be written");
+        }
     }
 
 
@@ -643,8 +1881,11 @@ mod test {
         let base_path = "./vectors/bnpair";
         let test_name = "bnpair";
 
+        let params = RunParameters::from_env(10, 1000);
+        let run_started = std::time::SystemTime::now();
+
         let data_fn = || {
-            generate_bnpair_vectors(10, 1000)
+            generate_bnpair_vectors(&params)
         };
 
         let transformer_fn = |a: (Vec<u8>, [u8; 32])| {
@@ -657,18 +1898,72 @@ mod test {
             format!("For {} pairs", num_pairs)
         };
 
-        let csv_writer_fn = make_csv_writer_for_path(base_path);
-        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name);
+        let spool = RecordSpool::shared(test_name);
+
+        let csv_writer_fn = make_csv_writer_for_path(base_path, &spool);
+        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name, &spool);
+        let timings_writer_fn = make_timings_writer_for_path_and_test_name(base_path, test_name, params.gas_rate.gas_per_second);
+        let state_test_writer_fn = make_state_test_writer_for_path_name_and_address(base_path, test_name, 0x08, &spool);
+        let fail_json_writer_fn = make_fail_json_writer_for_path_and_test_name(base_path, test_name);
         
         perform_measurements(
             write,
             crate::pricers::bnpair_pricer(),
             crate::pricers::bnpair_pricer(),
+            params.gas_rate.gas_per_second,
+            data_fn,
+            transformer_fn,
+            vec![csv_writer_fn, json_writer_fn, state_test_writer_fn, fail_json_writer_fn, timings_writer_fn],
+            ann_fn
+        ).expect("vectors must be written");
+
+        if write {
+            crate::manifest::write_manifest(base_path, test_name, &params, &["current", "proposed"], run_started).expect("manifest must be written");
+        }
+    }
+
+    fn do_ecrecover(write: bool) {
+        let base_path = "./vectors/ecrecover";
+        let test_name = "ecrecover";
+
+        let params = RunParameters::from_env(10, 10000);
+        let run_started = std::time::SystemTime::now();
+
+        let data_fn = || {
+            generate_ecrecover_vectors(&params)
+        };
+
+        let transformer_fn = |a: ([u8; 128], [u8; 32])| {
+            let (a, b) = a;
+
+            (a.to_vec(), encode_ecrecover_output(&b))
+        };
+
+        let ann_fn = |_: u64| {
+            String::from("")
+        };
+
+        let spool = RecordSpool::shared(test_name);
+
+        let csv_writer_fn = make_csv_writer_for_path(base_path, &spool);
+        let json_writer_fn = make_json_writer_for_path_and_test_name(base_path, test_name, &spool);
+        let timings_writer_fn = make_timings_writer_for_path_and_test_name(base_path, test_name, params.gas_rate.gas_per_second);
+        let state_test_writer_fn = make_state_test_writer_for_path_name_and_address(base_path, test_name, 0x01, &spool);
+
+        perform_measurements(
+            write,
+            crate::pricers::ecrecover_pricer(),
+            crate::pricers::ecrecover_pricer(),
+            params.gas_rate.gas_per_second,
             data_fn,
             transformer_fn,
-            vec![csv_writer_fn, json_writer_fn],
+            vec![csv_writer_fn, json_writer_fn, state_test_writer_fn, timings_writer_fn],
             ann_fn
-        );
+        ).expect("vectors must be written");
+
+        if write {
+            crate::manifest::write_manifest(base_path, test_name, &params, &["current", "proposed"], run_started).expect("manifest must be written");
+        }
     }
 
     #[test]
@@ -730,4 +2025,188 @@ mod test {
     fn try_bnpair() {
         do_bnpair(false);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn generate_ecrecover() {
+        do_ecrecover(true);
+    }
+
+    #[test]
+    fn try_ecrecover() {
+        do_ecrecover(false);
+    }
+
+    #[test]
+    fn test_length_sweep_parse() {
+        let sweep = LengthSweep::parse("0..=16:8, 1..=8*2, 4, 4, 0..=2").unwrap();
+        assert_eq!(sweep.lengths(), vec![0, 1, 2, 4, 8, 16]);
+
+        let sweep = LengthSweep::parse("boundaries,boundaries,64").unwrap();
+        assert_eq!(sweep.lengths(), HASH_BLOCK_BOUNDARIES.to_vec());
+
+        let sweep = LengthSweep::parse("1_024..=4_096*4").unwrap();
+        assert_eq!(sweep.lengths(), vec![1024, 4096]);
+
+        // the end is not reached by a step
+        let sweep = LengthSweep::parse("0..=20:8").unwrap();
+        assert_eq!(sweep.lengths(), vec![0, 8, 16]);
+
+        assert!(LengthSweep::parse("0..=256:0").is_err());
+        assert!(LengthSweep::parse("0..=256*2").is_err());
+        assert!(LengthSweep::parse("1..=256*1").is_err());
+        assert!(LengthSweep::parse("").is_err());
+        assert!(LengthSweep::parse("16..=8").is_err());
+        assert!(LengthSweep::parse("-1").is_err());
+        assert!(LengthSweep::parse("0..=x:8").is_err());
+    }
+
+    #[test]
+    fn test_hash_block_boundaries() {
+        use crate::fitting::hash_blocks;
+
+        let mut sorted = HASH_BLOCK_BOUNDARIES.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted, HASH_BLOCK_BOUNDARIES.to_vec());
+
+        for blocks in 1..=3u64 {
+            let last_fitting = (blocks * 64 - 9) as usize;
+            let multiple = (blocks * 64) as usize;
+            for len in [last_fitting - 1, last_fitting, last_fitting + 1, last_fitting + 2, multiple - 1, multiple, multiple + 1].iter() {
+                assert!(HASH_BLOCK_BOUNDARIES.contains(len), "{} is not a boundary", len);
+            }
+            assert_eq!(hash_blocks(last_fitting as u64), blocks);
+            assert_eq!(hash_blocks(last_fitting as u64 + 1), blocks + 1);
+        }
+    }
+
+    // Fails to add records while `failures_left` is not zero
+    struct FlakyWriter {
+        failures_left: usize,
+        added: std::rc::Rc<std::cell::RefCell<Vec<String>>>
+    }
+
+    impl BenchmarkDataWriter for FlakyWriter {
+        fn add_per_scalar_input(&mut self, record: &VectorRecord) -> std::io::Result<()> {
+            if self.failures_left > 0 {
+                self.failures_left -= 1;
+                return Err(std::io::Error::new(std::io::ErrorKind::Other, "disk is full"));
+            }
+            self.added.borrow_mut().push(record.label.to_string());
+
+            Ok(())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn set_base_path(&mut self, _base_path: &str) {}
+    }
+
+    #[test]
+    fn test_writer_errors_keep_records() {
+        let timing = TimingStats::default();
+        let record = |label: &'static str| VectorRecord {
+            family: "",
+            scalar: 1,
+            label,
+            seed: [0u8; 16],
+            input: &[],
+            output: &[],
+            expected_error: None,
+            timing: &timing,
+            gas: 0,
+            current_gas: 0,
+            proposed_gas: 0
+        };
+
+        // records that failed are retried in order with the next ones
+        let added = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut writer = RetryingWriter::new(box_writer(FlakyWriter { failures_left: 2, added: added.clone() }));
+        for label in ["0", "1", "2"].iter() {
+            writer.add(&record(label));
+        }
+        assert!(writer.flush().is_ok());
+        assert_eq!(*added.borrow(), vec!["0", "1", "2"]);
+
+        // records that can't be added are reported when flushing
+        let added = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut writer = RetryingWriter::new(box_writer(FlakyWriter { failures_left: usize::MAX, added: added.clone() }));
+        writer.add(&record("0"));
+        let (_, e) = writer.flush().err().expect("pending record is reported");
+        assert!(e.to_string().contains("1 vector(s) could not be added"));
+    }
+
+    #[test]
+    fn try_regenerate_vectors() {
+        let mut params = RunParameters::new(2, 1, GasRate::default());
+        params.rng_seed = parse_rng_seed("12345").unwrap();
+
+        for data_point in generate_sha256_vectors(&params).into_iter() {
+            for v in data_point.vectors.into_iter() {
+                let (input, output) = v.vector;
+                assert_eq!(regenerate_vector("sha256", "", data_point.scalar, v.seed).unwrap(), (input, Ok(output.to_vec())));
+            }
+        }
+
+        for data_point in generate_bnpair_vectors(&params).into_iter() {
+            for v in data_point.vectors.into_iter() {
+                let (input, output) = v.vector;
+                let expected = match v.expected_error {
+                    Some(e) => Err(e),
+                    None => Ok(output.to_vec())
+                };
+                assert_eq!(regenerate_vector("bnpair", &data_point.family, data_point.scalar, v.seed).unwrap(), (input, expected));
+            }
+        }
+
+        for data_point in generate_bn_add_vectors(&params).into_iter() {
+            for v in data_point.vectors.into_iter() {
+                let (input, output) = v.vector;
+                let expected = match v.expected_error {
+                    Some(e) => Err(e),
+                    None => Ok(output.to_vec())
+                };
+                assert_eq!(regenerate_vector("bnadd", &data_point.family, data_point.scalar, v.seed).unwrap(), (input.to_vec(), expected));
+            }
+        }
+
+        for data_point in generate_bn_mul_vectors(&params).into_iter() {
+            for v in data_point.vectors.into_iter() {
+                let (input, output) = v.vector;
+                let expected = match v.expected_error {
+                    Some(e) => Err(e),
+                    None => Ok(output.to_vec())
+                };
+                assert_eq!(regenerate_vector("bnmul", &data_point.family, data_point.scalar, v.seed).unwrap(), (input.to_vec(), expected));
+            }
+        }
+
+        for data_point in generate_ecrecover_vectors(&params).into_iter() {
+            for v in data_point.vectors.into_iter() {
+                let (input, output) = v.vector;
+                assert_eq!(regenerate_vector("ecrecover", &data_point.family, data_point.scalar, v.seed).unwrap(), (input.to_vec(), Ok(output.to_vec())));
+            }
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn regenerate_vector_from_seed() {
+        let precompile = std::env::var("BENCH_PRECOMPILE").expect("precompile name must be set");
+        let scalar = std::env::var("BENCH_SCALAR").ok()
+            .map(|s| s.parse::<u64>().expect("parameter must be an integer"))
+            .unwrap_or(0);
+        let family = std::env::var("BENCH_FAMILY").unwrap_or_default();
+        let seed = std::env::var("BENCH_VECTOR_SEED").expect("vector seed must be set");
+        let seed = parse_rng_seed(&seed).expect("vector seed must be valid");
+
+        let (input, output) = regenerate_vector(&precompile, &family, scalar, seed).unwrap();
+        println!("Input: {}", hex::encode(&input));
+        match output {
+            Ok(output) => println!("Expected: {}", hex::encode(&output)),
+            Err(e) => println!("Expected error: {}", e.message())
+        }
+    }
+}
@@ -1,35 +1,116 @@
-pub fn read_fr(reader: &[u8]) -> Result<bn::Fr, &'static str> {
+use super::runners::PrecompileError;
+
+pub fn read_fr(reader: &[u8]) -> Result<bn::Fr, PrecompileError> {
+    if reader.len() != 32 {
+        return Err(PrecompileError::InvalidLength("Invalid field element length, must be 32 bytes"));
+    }
+
     let mut buf = [0u8; 32];
     buf.copy_from_slice(&reader);
 
-    bn::Fr::from_slice(&buf[0..32]).map_err(|_| "Invalid field element")
+    bn::Fr::from_slice(&buf[0..32]).map_err(|_| PrecompileError::NotBelowModulus("Invalid field element"))
 }
 
-pub fn read_point(reader: &[u8]) -> Result<bn::G1, &'static str> {
+pub fn read_point(reader: &[u8]) -> Result<bn::G1, PrecompileError> {
     use bn::{Fq, AffineG1, G1, Group};
 
+    if reader.len() != 64 {
+        return Err(PrecompileError::InvalidLength("Invalid point length, must be 64 bytes"));
+    }
+
     let mut buf = [0u8; 32];
 
     buf.copy_from_slice(&reader[0..32]);
 
-    let px = Fq::from_slice(&buf[0..32]).map_err(|_| "Invalid point x coordinate")?;
+    let px = Fq::from_slice(&buf[0..32]).map_err(|_| PrecompileError::NotBelowModulus("Invalid point x coordinate"))?;
 
     buf.copy_from_slice(&reader[32..64]);
 
-    let py = Fq::from_slice(&buf[0..32]).map_err(|_| "Invalid point y coordinate")?;
+    let py = Fq::from_slice(&buf[0..32]).map_err(|_| PrecompileError::NotBelowModulus("Invalid point y coordinate"))?;
     Ok(
         if px == Fq::zero() && py == Fq::zero() {
             G1::zero()
         } else {
-            AffineG1::new(px, py).map_err(|_| "Invalid curve point")?.into()
+            AffineG1::new(px, py).map_err(|_| PrecompileError::NotOnCurve("Invalid curve point"))?.into()
         }
     )
 }
 
+// Reads a 128 byte encoding of a G2 point, imaginary coefficients first. Point has to be on the
+// twist curve and in the prime order subgroup, both are checked explicitly rather than relying on
+// `AffineG2::new` to do that
+pub fn read_g2_point(reader: &[u8]) -> Result<bn::G2, PrecompileError> {
+    use bn::{Fq, Fq2, G2, Group};
+
+    if reader.len() != 128 {
+        return Err(PrecompileError::InvalidLength("Invalid b argument length, must be 128 bytes"));
+    }
+
+    let x_imaginary = Fq::from_slice(&reader[0..32]).map_err(|_| PrecompileError::NotBelowModulus("Invalid b argument imaginary coeff x coordinate"))?;
+    let x_real = Fq::from_slice(&reader[32..64]).map_err(|_| PrecompileError::NotBelowModulus("Invalid b argument real coeff x coordinate"))?;
+    let y_imaginary = Fq::from_slice(&reader[64..96]).map_err(|_| PrecompileError::NotBelowModulus("Invalid b argument imaginary coeff y coordinate"))?;
+    let y_real = Fq::from_slice(&reader[96..128]).map_err(|_| PrecompileError::NotBelowModulus("Invalid b argument real coeff y coordinate"))?;
+
+    let x = Fq2::new(x_real, x_imaginary);
+    let y = Fq2::new(y_real, y_imaginary);
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2::zero());
+    }
+
+    if y * y != x * x * x + G2::b() {
+        return Err(PrecompileError::NotOnCurve("Invalid b argument - not on curve"));
+    }
+
+    let point = G2::new(x, y, Fq2::one());
+    if !is_in_g2_subgroup(point) {
+        return Err(PrecompileError::NotInSubgroup("Invalid b argument - not in subgroup"));
+    }
+
+    Ok(point)
+}
+
+// r * P = 0, computed as (r - 1) * P + P
+pub fn is_in_g2_subgroup(point: bn::G2) -> bool {
+    use bn::{Fr, Group};
+
+    (point * (-Fr::one()) + point).is_zero()
+}
+
 pub fn encode_g1_point(p: bn::AffineG1) -> [u8; 64] {
     let mut output = [0u8; 64];
     p.x().to_big_endian(&mut output[0..32]).expect("Cannot fail since 0..32 is 32-byte length");
     p.y().to_big_endian(&mut output[32..64]).expect("Cannot fail since 32..64 is 32-byte length");
 
     output
-}
\ No newline at end of file
+}
+
+// Writes into a temporary file next to `path` and renames it over `path` once complete,
+// creating missing parent directories first
+pub fn write_atomically<F: FnOnce(&mut std::io::BufWriter<std::fs::File>) -> std::io::Result<()>>(path: &str, writer_fn: F) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let path = std::path::Path::new(path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let file_name = path.file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "path must point to a file"))?
+        .to_string_lossy()
+        .into_owned();
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    let result = std::fs::File::create(&tmp_path).and_then(|file| {
+        let mut writer = std::io::BufWriter::new(file);
+        writer_fn(&mut writer)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()
+    });
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    std::fs::rename(&tmp_path, path)
+}
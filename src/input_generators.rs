@@ -1,6 +1,6 @@
 use rand::{Rng};
 
-use super::runners;
+use super::runners::{self, PrecompileError};
 use super::helpers;
 
 pub fn generate_random_bytes_for_length<R: Rng>(len: usize, rng: &mut R) -> Vec<u8> {
@@ -25,6 +25,13 @@ pub fn generate_ripemd_vector_for_len<R: Rng>(input_len: usize, rng: &mut R) ->
 }
 
 pub fn generate_blake2f_vector_for_num_rounds<R: Rng>(rounds: usize, rng: &mut R) -> (Vec<u8>, [u8; 64]) {
+    let input = generate_blake2f_input_for_num_rounds(rounds, rng);
+    let output = runners::run_blake2f(&input).expect("flag and length are valid");
+
+    (input, output)
+}
+
+fn generate_blake2f_input_for_num_rounds<R: Rng>(rounds: usize, rng: &mut R) -> Vec<u8> {
     use byteorder::{BigEndian};
     use byteorder::{WriteBytesExt};
 
@@ -37,7 +44,163 @@ pub fn generate_blake2f_vector_for_num_rounds<R: Rng>(rounds: usize, rng: &mut R
     let last_byte = input[BLAKE2_F_ARG_LEN-1];
     input[BLAKE2_F_ARG_LEN-1] = last_byte & 1u8;
 
+    input
+}
+
+// Round counts and final flag values outside of the random vectors, and invalid inputs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blake2fFamily {
+    // up to the maximal number of rounds, parameter is a number of rounds
+    LargeRounds,
+    // final block indicator flag is set, parameter is a number of rounds
+    FinalBlock,
+    // final block indicator flag is not set, parameter is a number of rounds
+    NotFinalBlock,
+    // flag byte is 2 to 255, parameter is a number of rounds
+    InvalidFlag,
+    // parameter is an input length other than 213 bytes
+    InvalidLength
+}
+
+impl Blake2fFamily {
+    pub const ALL: [Blake2fFamily; 5] = [
+        Blake2fFamily::LargeRounds,
+        Blake2fFamily::FinalBlock,
+        Blake2fFamily::NotFinalBlock,
+        Blake2fFamily::InvalidFlag,
+        Blake2fFamily::InvalidLength
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Blake2fFamily::LargeRounds => "large_rounds",
+            Blake2fFamily::FinalBlock => "final_block",
+            Blake2fFamily::NotFinalBlock => "not_final_block",
+            Blake2fFamily::InvalidFlag => "invalid_flag",
+            Blake2fFamily::InvalidLength => "invalid_length"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|f| f.name() == name).cloned()
+    }
+
+    // At one gas per round 1M, 10M and 30M rounds cost as much as a transaction or a block
+    pub fn params(&self) -> Vec<u64> {
+        match self {
+            Blake2fFamily::LargeRounds => vec![256, 1024, 4096, 16384, 65536, 262144, 1_000_000, 10_000_000, 30_000_000, u32::MAX as u64],
+            Blake2fFamily::FinalBlock | Blake2fFamily::NotFinalBlock => vec![0, 1, 12, 128, 1024],
+            Blake2fFamily::InvalidFlag => vec![0, 12, 1024],
+            Blake2fFamily::InvalidLength => vec![0, 4, 212, 214, 256]
+        }
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, Blake2fFamily::InvalidFlag | Blake2fFamily::InvalidLength)
+    }
+}
+
+// Output is an error for invalid families
+pub fn generate_blake2f_vector_for_family<R: Rng>(family: Blake2fFamily, param: u64, rng: &mut R) -> (Vec<u8>, Result<[u8; 64], PrecompileError>) {
+    const BLAKE2_F_ARG_LEN: usize = 213;
+
+    let mut input = match family {
+        // 12 rounds as in BLAKE2b
+        Blake2fFamily::InvalidLength => generate_blake2f_input_for_num_rounds(12, rng),
+        _ => generate_blake2f_input_for_num_rounds(param as usize, rng)
+    };
+
+    match family {
+        Blake2fFamily::LargeRounds => {},
+        Blake2fFamily::FinalBlock => input[BLAKE2_F_ARG_LEN-1] = 1u8,
+        Blake2fFamily::NotFinalBlock => input[BLAKE2_F_ARG_LEN-1] = 0u8,
+        Blake2fFamily::InvalidFlag => input[BLAKE2_F_ARG_LEN-1] = rng.gen_range(2u16, 256u16) as u8,
+        Blake2fFamily::InvalidLength => input.resize(param as usize, 0u8)
+    }
+
     let output = runners::run_blake2f(&input);
+    assert_eq!(output.is_err(), family.is_invalid());
+
+    (input, output)
+}
+
+// Malformed signatures, the precompile returns no output for them instead of failing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EcrecoverFamily {
+    // v is a random word other than 27 and 28
+    InvalidV,
+    ZeroR,
+    ZeroS,
+    // r is in [n, 2^256)
+    RAboveOrder,
+    // s is in [n, 2^256)
+    SAboveOrder
+}
+
+impl EcrecoverFamily {
+    pub const ALL: [EcrecoverFamily; 5] = [
+        EcrecoverFamily::InvalidV,
+        EcrecoverFamily::ZeroR,
+        EcrecoverFamily::ZeroS,
+        EcrecoverFamily::RAboveOrder,
+        EcrecoverFamily::SAboveOrder
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EcrecoverFamily::InvalidV => "invalid_v",
+            EcrecoverFamily::ZeroR => "zero_r",
+            EcrecoverFamily::ZeroS => "zero_s",
+            EcrecoverFamily::RAboveOrder => "r_above_order",
+            EcrecoverFamily::SAboveOrder => "s_above_order"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|f| f.name() == name).cloned()
+    }
+}
+
+fn secp256k1_group_order() -> num_bigint::BigUint {
+    num_bigint::BigUint::parse_bytes(b"fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141", 16)
+        .expect("group order is a valid hex")
+}
+
+// Input is `hash || v || r || s` with a random hash, output is always all zeros
+pub fn generate_ecrecover_vector_for_family<R: Rng>(family: EcrecoverFamily, rng: &mut R) -> ([u8; 128], [u8; 32]) {
+    use num_bigint::BigUint;
+
+    let order = secp256k1_group_order();
+    let below_order = |rng: &mut R| {
+        encode_biguint(&(BigUint::from_bytes_be(&generate_random_bytes_for_length(32, rng)) % &order))
+    };
+    let above_order = |rng: &mut R| {
+        let span = (BigUint::from(1u64) << 256) - &order;
+        encode_biguint(&(&order + BigUint::from_bytes_be(&generate_random_bytes_for_length(32, rng)) % span))
+    };
+
+    let mut input = [0u8; 128];
+    input[0..32].copy_from_slice(&generate_random_bytes_for_length(32, rng));
+    input[63] = 27 + rng.gen_range(0u8, 2u8);
+    input[64..96].copy_from_slice(&below_order(rng));
+    input[96..128].copy_from_slice(&below_order(rng));
+
+    match family {
+        EcrecoverFamily::InvalidV => loop {
+            let v = generate_random_bytes_for_length(32, rng);
+            if v[0..31] != [0u8; 31] || (v[31] != 27 && v[31] != 28) {
+                input[32..64].copy_from_slice(&v);
+                break;
+            }
+        },
+        EcrecoverFamily::ZeroR => input[64..96].copy_from_slice(&[0u8; 32]),
+        EcrecoverFamily::ZeroS => input[96..128].copy_from_slice(&[0u8; 32]),
+        EcrecoverFamily::RAboveOrder => input[64..96].copy_from_slice(&above_order(rng)),
+        EcrecoverFamily::SAboveOrder => input[96..128].copy_from_slice(&above_order(rng))
+    }
+
+    let output = runners::run_ecrecover(&input);
+    assert_eq!(output, [0u8; 32]);
 
     (input, output)
 }
@@ -68,8 +231,13 @@ pub fn generate_random_g1_points<R: Rng>(rng: &mut R) -> bn::AffineG1 {
     p1
 }
 
+// Largest Hamming weight below the group order r = 0x30644e...f0000001: 253 bits set out of the
+// 254 bits of r, so double-and-add does the most additions on a scalar in the valid range
 pub fn worst_case_scalar_for_double_and_add() -> [u8; 32] {
-    [0xff; 32]
+    let mut scalar = [0xff; 32];
+    scalar[0] = 0x2f;
+
+    scalar
 }
 
 pub fn generate_bnadd_vector<R: Rng>(rng: &mut R) -> ([u8; 128], [u8; 64]) {
@@ -86,13 +254,193 @@ pub fn generate_bnadd_vector<R: Rng>(rng: &mut R) -> ([u8; 128], [u8; 64]) {
     (&mut input[0..64]).write(&p1_encoding).unwrap();
     (&mut input[64..128]).write(&p2_encoding).unwrap();
 
-    let output = runners::run_bn_add(&input);
+    let output = runners::run_bn_add(&input).expect("random points are valid");
 
     assert!(&output[..] != &[0u8; 64][..]);
 
     (input, output)
 }
 
+// Edge cases of ECADD that random points never hit. Invalid families produce inputs the
+// precompile must reject
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BnAddFamily {
+    // P + P
+    Doubling,
+    // P + (-P)
+    Negation,
+    // P + infinity
+    Infinity,
+    // infinity + infinity
+    BothInfinity,
+    // coordinates of the first point are close to the field modulus
+    NearModulus,
+    InvalidOffCurve,
+    // x coordinate is not reduced, i.e. x + p
+    InvalidCoordinate
+}
+
+impl BnAddFamily {
+    pub const ALL: [BnAddFamily; 7] = [
+        BnAddFamily::Doubling,
+        BnAddFamily::Negation,
+        BnAddFamily::Infinity,
+        BnAddFamily::BothInfinity,
+        BnAddFamily::NearModulus,
+        BnAddFamily::InvalidOffCurve,
+        BnAddFamily::InvalidCoordinate
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BnAddFamily::Doubling => "doubling",
+            BnAddFamily::Negation => "negation",
+            BnAddFamily::Infinity => "infinity",
+            BnAddFamily::BothInfinity => "both_infinity",
+            BnAddFamily::NearModulus => "near_modulus",
+            BnAddFamily::InvalidOffCurve => "invalid_off_curve",
+            BnAddFamily::InvalidCoordinate => "invalid_coordinate"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|f| f.name() == name).cloned()
+    }
+}
+
+// Output is an error for invalid families
+pub fn generate_bnadd_vector_for_family<R: Rng>(family: BnAddFamily, rng: &mut R) -> ([u8; 128], Result<[u8; 64], PrecompileError>) {
+    let mut input = [0u8; 128];
+
+    let p = helpers::encode_g1_point(generate_random_g1_points(rng));
+    match family {
+        BnAddFamily::Doubling => {
+            input[0..64].copy_from_slice(&p);
+            input[64..128].copy_from_slice(&p);
+        },
+        BnAddFamily::Negation => {
+            input[0..64].copy_from_slice(&p);
+            input[64..128].copy_from_slice(&negate_g1_encoding(&p));
+        },
+        BnAddFamily::Infinity => {
+            input[0..64].copy_from_slice(&p);
+        },
+        BnAddFamily::BothInfinity => {},
+        BnAddFamily::NearModulus => {
+            // about half of the offsets give a point, x = p - 1 is always one
+            let offset = 1 + (rng.next_u32() as u64 & 0xffff);
+            let near = match g1_point_below_modulus(offset) {
+                Some(near) => near,
+                None => g1_point_below_modulus(1).expect("x = p - 1 is on curve")
+            };
+            input[0..64].copy_from_slice(&near);
+            input[64..128].copy_from_slice(&p);
+        },
+        BnAddFamily::InvalidOffCurve => {
+            input[0..64].copy_from_slice(&off_curve_point(&p));
+            input[64..128].copy_from_slice(&p);
+        },
+        BnAddFamily::InvalidCoordinate => {
+            let unreduced = add_modulus(&p[0..32]);
+            input[0..32].copy_from_slice(&unreduced);
+            input[32..64].copy_from_slice(&p[32..64]);
+            input[64..128].copy_from_slice(&p);
+        }
+    }
+
+    let output = runners::run_bn_add(&input);
+    match family {
+        BnAddFamily::Negation | BnAddFamily::BothInfinity => assert_eq!(output, Ok([0u8; 64])),
+        BnAddFamily::Infinity => assert_eq!(output, Ok(p)),
+        BnAddFamily::InvalidOffCurve | BnAddFamily::InvalidCoordinate => assert!(output.is_err()),
+        BnAddFamily::Doubling | BnAddFamily::NearModulus => assert!(output.is_ok())
+    }
+
+    (input, output)
+}
+
+fn bn254_field_modulus() -> num_bigint::BigUint {
+    num_bigint::BigUint::parse_bytes(b"30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47", 16)
+        .expect("modulus is a valid hex")
+}
+
+fn bn254_group_order() -> num_bigint::BigUint {
+    num_bigint::BigUint::parse_bytes(b"30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001", 16)
+        .expect("group order is a valid hex")
+}
+
+fn encode_biguint(value: &num_bigint::BigUint) -> [u8; 32] {
+    let bytes = value.to_bytes_be();
+    assert!(bytes.len() <= 32);
+
+    let mut encoding = [0u8; 32];
+    encoding[(32 - bytes.len())..].copy_from_slice(&bytes);
+
+    encoding
+}
+
+// Encoding of the curve point with x = p - offset and the larger of its y coordinates,
+// or `None` if x^3 + 3 is not a square
+pub fn g1_point_below_modulus(offset: u64) -> Option<[u8; 64]> {
+    use num_bigint::BigUint;
+
+    let modulus = bn254_field_modulus();
+    let x = &modulus - BigUint::from(offset);
+    let rhs = (x.modpow(&BigUint::from(3u64), &modulus) + BigUint::from(3u64)) % &modulus;
+    // p = 3 mod 4, so a square root is rhs^((p + 1) / 4)
+    let exponent = (&modulus + BigUint::from(1u64)) >> 2;
+    let y = rhs.modpow(&exponent, &modulus);
+    if (&y * &y) % &modulus != rhs {
+        return None;
+    }
+    let other_y = &modulus - &y;
+    let y = if other_y > y { other_y } else { y };
+
+    let mut encoding = [0u8; 64];
+    encoding[0..32].copy_from_slice(&encode_biguint(&x));
+    encoding[32..64].copy_from_slice(&encode_biguint(&y));
+
+    Some(encoding)
+}
+
+// (x, y) -> (x, p - y) for points not at infinity
+pub fn negate_g1_encoding(point: &[u8; 64]) -> [u8; 64] {
+    use num_bigint::BigUint;
+
+    let y = BigUint::from_bytes_be(&point[32..64]);
+    let mut negated = *point;
+    negated[32..64].copy_from_slice(&encode_biguint(&(bn254_field_modulus() - y)));
+
+    negated
+}
+
+// Field element + p, still fits into 32 bytes since p < 2^254
+pub fn add_modulus(element: &[u8]) -> [u8; 32] {
+    let value = num_bigint::BigUint::from_bytes_be(element);
+
+    encode_biguint(&(value + bn254_field_modulus()))
+}
+
+// Encoding of a G1 or G2 point with the last coordinate word (y, or the real coefficient of y for
+// G2) incremented. (x, y + 1) is on curve only if y + 1 = -y, and y + 1 = p is rejected as well
+fn off_curve_point(encoding: &[u8]) -> Vec<u8> {
+    let mut off_curve = encoding.to_vec();
+    let len = off_curve.len();
+    increment_be(&mut off_curve[len - 32..]);
+
+    off_curve
+}
+
+fn increment_be(bytes: &mut [u8]) {
+    for b in bytes.iter_mut().rev() {
+        let (incremented, overflow) = b.overflowing_add(1);
+        *b = incremented;
+        if !overflow {
+            break;
+        }
+    }
+}
+
 pub fn generate_bnmul_vector<R: Rng>(rng: &mut R) -> ([u8; 96], [u8; 64]) {
     use std::io::Write;
 
@@ -105,13 +453,182 @@ pub fn generate_bnmul_vector<R: Rng>(rng: &mut R) -> ([u8; 96], [u8; 64]) {
     (&mut input[0..64]).write(&p1_encoding).unwrap();
     (&mut input[64..96]).write(&worst_case_scalar_for_double_and_add()).unwrap();
 
-    let output = runners::run_bn_mul(&input);
+    let output = runners::run_bn_mul(&input).expect("random points are valid");
 
     assert!(&output[..] != &[0u8; 64][..]);
 
     (input, output)
 }
 
+// Scalar shapes of ECMUL. Double-and-add cost depends on the bit length and Hamming weight,
+// wNAF and GLV implementations depend on the density of the non-adjacent form instead.
+// Scalars of the special families are not reduced by the precompile, so r and above must work
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BnMulFamily {
+    // random scalar with exactly `param` bits
+    BitLength,
+    // random 253-bit scalar with `param` bits set
+    HammingWeight,
+    // random scalar with `param` non-zero digits in its non-adjacent form
+    NafWeight,
+    Zero,
+    One,
+    OrderMinusOne,
+    Order,
+    // random scalar in [r, 2^256)
+    AboveOrder,
+    // point has its y coordinate incremented, random scalar below r
+    InvalidOffCurve,
+    // x coordinate of the point is not reduced, i.e. x + p, random scalar below r
+    InvalidCoordinate
+}
+
+impl BnMulFamily {
+    pub const ALL: [BnMulFamily; 10] = [
+        BnMulFamily::BitLength,
+        BnMulFamily::HammingWeight,
+        BnMulFamily::NafWeight,
+        BnMulFamily::Zero,
+        BnMulFamily::One,
+        BnMulFamily::OrderMinusOne,
+        BnMulFamily::Order,
+        BnMulFamily::AboveOrder,
+        BnMulFamily::InvalidOffCurve,
+        BnMulFamily::InvalidCoordinate
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BnMulFamily::BitLength => "bit_length",
+            BnMulFamily::HammingWeight => "hamming_weight",
+            BnMulFamily::NafWeight => "naf_weight",
+            BnMulFamily::Zero => "scalar_zero",
+            BnMulFamily::One => "scalar_one",
+            BnMulFamily::OrderMinusOne => "scalar_order_minus_one",
+            BnMulFamily::Order => "scalar_order",
+            BnMulFamily::AboveOrder => "scalar_above_order",
+            BnMulFamily::InvalidOffCurve => "invalid_off_curve",
+            BnMulFamily::InvalidCoordinate => "invalid_coordinate"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|f| f.name() == name).cloned()
+    }
+
+    // Families of a single scalar have only parameter 0
+    pub fn params(&self) -> Vec<u64> {
+        match self {
+            BnMulFamily::BitLength => vec![1, 2, 8, 16, 32, 64, 128, 192, 253, 254],
+            BnMulFamily::HammingWeight => vec![1, 8, 32, 64, 128, 192, 253],
+            BnMulFamily::NafWeight => vec![1, 8, 32, 64, 96, 126],
+            _ => vec![0]
+        }
+    }
+}
+
+// Output is an error for invalid families
+pub fn generate_bnmul_vector_for_family<R: Rng>(family: BnMulFamily, param: u64, rng: &mut R) -> ([u8; 96], Result<[u8; 64], PrecompileError>) {
+    let mut input = [0u8; 96];
+
+    let p = helpers::encode_g1_point(generate_random_g1_points(rng));
+    input[0..64].copy_from_slice(&p);
+    input[64..96].copy_from_slice(&generate_bnmul_scalar(family, param, rng));
+
+    match family {
+        BnMulFamily::InvalidOffCurve => {
+            let off_curve = off_curve_point(&input[0..64]);
+            input[0..64].copy_from_slice(&off_curve);
+        },
+        BnMulFamily::InvalidCoordinate => {
+            let unreduced = add_modulus(&p[0..32]);
+            input[0..32].copy_from_slice(&unreduced);
+        },
+        _ => {}
+    }
+
+    let output = runners::run_bn_mul(&input);
+    match family {
+        BnMulFamily::Zero | BnMulFamily::Order => assert_eq!(output, Ok([0u8; 64])),
+        BnMulFamily::One => assert_eq!(output, Ok(p)),
+        BnMulFamily::OrderMinusOne => assert_eq!(output, Ok(negate_g1_encoding(&p))),
+        BnMulFamily::InvalidOffCurve | BnMulFamily::InvalidCoordinate => assert!(output.is_err()),
+        _ => assert!(output.expect("point is valid") != [0u8; 64])
+    }
+
+    (input, output)
+}
+
+pub fn generate_bnmul_scalar<R: Rng>(family: BnMulFamily, param: u64, rng: &mut R) -> [u8; 32] {
+    use num_bigint::BigUint;
+    use num_traits::{One, Zero};
+
+    let order = bn254_group_order();
+    let scalar = match family {
+        BnMulFamily::BitLength => {
+            assert!((1..=254).contains(&param));
+            let bits = param as usize;
+            loop {
+                let mut candidate = BigUint::from_bytes_be(&generate_random_bytes_for_length(32, rng));
+                candidate %= BigUint::one() << (bits - 1);
+                candidate += BigUint::one() << (bits - 1);
+                if candidate < order {
+                    break candidate;
+                }
+            }
+        },
+        BnMulFamily::HammingWeight => {
+            // 2^253 < r, so any 253-bit scalar is below the order
+            assert!((1..=253).contains(&param));
+            let mut scalar = BigUint::zero();
+            for position in rand::seq::index::sample(rng, 253, param as usize).into_iter() {
+                scalar += BigUint::one() << position;
+            }
+
+            scalar
+        },
+        BnMulFamily::NafWeight => {
+            // digits of a 252-digit NAF, a pick of `param` slots out of `252 - param + 1` shifted by
+            // their rank gives positions that are at least two apart
+            assert!((1..=126).contains(&param));
+            let weight = param as usize;
+            let mut slots = rand::seq::index::sample(rng, 252 - weight + 1, weight).into_vec();
+            slots.sort_unstable();
+
+            let mut positive = BigUint::zero();
+            let mut negative = BigUint::zero();
+            for (rank, slot) in slots.iter().enumerate() {
+                let digit = BigUint::one() << (slot + rank);
+                // the leading digit is positive, so the scalar is positive
+                if rank == weight - 1 || rng.gen::<bool>() {
+                    positive += digit;
+                } else {
+                    negative += digit;
+                }
+            }
+
+            positive - negative
+        },
+        BnMulFamily::Zero => BigUint::zero(),
+        BnMulFamily::One => BigUint::one(),
+        BnMulFamily::OrderMinusOne => &order - BigUint::one(),
+        BnMulFamily::Order => order,
+        BnMulFamily::AboveOrder => {
+            loop {
+                let candidate = BigUint::from_bytes_be(&generate_random_bytes_for_length(32, rng));
+                if candidate >= order {
+                    break candidate;
+                }
+            }
+        },
+        BnMulFamily::InvalidOffCurve | BnMulFamily::InvalidCoordinate => {
+            BigUint::from_bytes_be(&generate_random_bytes_for_length(32, rng)) % order
+        }
+    };
+
+    encode_biguint(&scalar)
+}
+
 pub fn generate_bnpair_vector<R: Rng>(num_pairs: usize, rng: &mut R) -> (Vec<u8>, [u8; 32]) {
     use bn::{Group, AffineG1, AffineG2};
     
@@ -155,8 +672,221 @@ pub fn generate_bnpair_vector<R: Rng>(num_pairs: usize, rng: &mut R) -> (Vec<u8>
         offset += 32;
     }
 
+    let output = runners::run_bn_pair(&input).expect("random points are valid");
+
+    (input, output)
+}
+
+// Random pairs almost never multiply to one, so the successful check that SNARK verifiers
+// rely on needs dedicated vectors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BnPairFamily {
+    // e(a_1 P, b_1 Q) * ... * e(-(a_1 b_1 + ...) P, Q) = 1, a single pair is e(infinity, Q)
+    Balanced,
+    // every G1 point is at infinity
+    InfinityG1,
+    // every G2 point is at infinity
+    InfinityG2,
+    // last G2 point is on the twist curve but outside of the prime order subgroup
+    InvalidG2Subgroup,
+    // last G2 point has its y coordinate incremented
+    InvalidG2OffCurve,
+    // valid pairs followed by 1 to 191 extra bytes
+    InvalidLength
+}
+
+impl BnPairFamily {
+    pub const ALL: [BnPairFamily; 6] = [
+        BnPairFamily::Balanced,
+        BnPairFamily::InfinityG1,
+        BnPairFamily::InfinityG2,
+        BnPairFamily::InvalidG2Subgroup,
+        BnPairFamily::InvalidG2OffCurve,
+        BnPairFamily::InvalidLength
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BnPairFamily::Balanced => "balanced",
+            BnPairFamily::InfinityG1 => "infinity_g1",
+            BnPairFamily::InfinityG2 => "infinity_g2",
+            BnPairFamily::InvalidG2Subgroup => "invalid_g2_subgroup",
+            BnPairFamily::InvalidG2OffCurve => "invalid_g2_off_curve",
+            BnPairFamily::InvalidLength => "invalid_length"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|f| f.name() == name).cloned()
+    }
+}
+
+// Output is an error for invalid families
+pub fn generate_bnpair_vector_for_family<R: Rng>(family: BnPairFamily, num_pairs: usize, rng: &mut R) -> (Vec<u8>, Result<[u8; 32], PrecompileError>) {
+    use bn::{Group, Fr, G1, G2};
+
+    assert!(num_pairs > 0);
+
+    let mut input = Vec::with_capacity(num_pairs * (64 + 128));
+    let mut sum_of_products = Fr::zero();
+    for i in 0..num_pairs {
+        let last = i == num_pairs - 1;
+        let a = generate_random_fr(rng);
+        let b = generate_random_fr(rng);
+
+        let (p1, p2) = match family {
+            BnPairFamily::Balanced if last => (G1::one() * (-sum_of_products), G2::one()),
+            BnPairFamily::Balanced => {
+                sum_of_products = sum_of_products + a * b;
+                (G1::one() * a, G2::one() * b)
+            },
+            BnPairFamily::InfinityG1 => (G1::zero(), G2::one() * b),
+            BnPairFamily::InfinityG2 => (G1::one() * a, G2::zero()),
+            BnPairFamily::InvalidG2Subgroup | BnPairFamily::InvalidG2OffCurve | BnPairFamily::InvalidLength => (G1::one() * a, G2::one() * b)
+        };
+
+        input.extend_from_slice(&encode_g1_jacobian(p1));
+        match family {
+            BnPairFamily::InvalidG2Subgroup if last => input.extend_from_slice(&g2_point_outside_subgroup(rng)),
+            BnPairFamily::InvalidG2OffCurve if last => input.extend_from_slice(&off_curve_point(&encode_g2_jacobian(p2))),
+            _ => input.extend_from_slice(&encode_g2_jacobian(p2))
+        }
+    }
+
+    if family == BnPairFamily::InvalidLength {
+        let extra = rng.gen_range(1, 192);
+        input.extend(generate_random_bytes_for_length(extra, rng));
+    }
+
     let output = runners::run_bn_pair(&input);
+    match family {
+        BnPairFamily::InvalidG2Subgroup | BnPairFamily::InvalidG2OffCurve | BnPairFamily::InvalidLength => assert!(output.is_err()),
+        _ => assert_eq!(output.map(|o| o[31]), Ok(1))
+    }
 
     (input, output)
+}
+
+fn generate_random_fr<R: Rng>(rng: &mut R) -> bn::Fr {
+    let mut scalar_buffer = [0u8; 32];
+    rng.fill_bytes(&mut scalar_buffer);
+    scalar_buffer[0] = 0;
+
+    helpers::read_fr(&scalar_buffer).unwrap()
+}
+
+// Infinity is encoded as zeroes
+fn encode_g1_jacobian(p: bn::G1) -> [u8; 64] {
+    match bn::AffineG1::from_jacobian(p) {
+        Some(p) => helpers::encode_g1_point(p),
+        None => [0u8; 64]
+    }
+}
+
+// Imaginary coefficients go first, infinity is encoded as zeroes
+fn encode_g2_jacobian(p: bn::G2) -> [u8; 128] {
+    let mut output = [0u8; 128];
+    if let Some(p) = bn::AffineG2::from_jacobian(p) {
+        p.x().imaginary().to_big_endian(&mut output[0..32]).expect("Cannot fail since 0..32 is 32-byte length");
+        p.x().real().to_big_endian(&mut output[32..64]).expect("Cannot fail since 32..64 is 32-byte length");
+        p.y().imaginary().to_big_endian(&mut output[64..96]).expect("Cannot fail since 64..96 is 32-byte length");
+        p.y().real().to_big_endian(&mut output[96..128]).expect("Cannot fail since 96..128 is 32-byte length");
+    }
 
+    output
+}
+
+// Random point of the twist curve y^2 = x^3 + 3 / (9 + u), rejected if it happens to be in the
+// prime order subgroup (only one of about p points of the curve is)
+pub fn g2_point_outside_subgroup<R: Rng>(rng: &mut R) -> [u8; 128] {
+    use num_bigint::BigUint;
+
+    let modulus = bn254_field_modulus();
+    // 1 / (9 + u) = (9 - u) / 82
+    let inverse_82 = BigUint::from(82u64).modpow(&(&modulus - BigUint::from(2u64)), &modulus);
+    let twist_b = (
+        (BigUint::from(27u64) * &inverse_82) % &modulus,
+        ((&modulus - BigUint::from(3u64)) * &inverse_82) % &modulus
+    );
+
+    loop {
+        let x = (
+            BigUint::from_bytes_be(&generate_random_bytes_for_length(32, rng)) % &modulus,
+            BigUint::from_bytes_be(&generate_random_bytes_for_length(32, rng)) % &modulus
+        );
+        let x_cubed = fq2_mul(&fq2_mul(&x, &x, &modulus), &x, &modulus);
+        let rhs = ((x_cubed.0 + &twist_b.0) % &modulus, (x_cubed.1 + &twist_b.1) % &modulus);
+        let y = match fq2_sqrt(&rhs, &modulus) {
+            Some(y) => y,
+            None => continue
+        };
+
+        let mut encoding = [0u8; 128];
+        encoding[0..32].copy_from_slice(&encode_biguint(&x.1));
+        encoding[32..64].copy_from_slice(&encode_biguint(&x.0));
+        encoding[64..96].copy_from_slice(&encode_biguint(&y.1));
+        encoding[96..128].copy_from_slice(&encode_biguint(&y.0));
+
+        let read = |offset: usize| bn::Fq::from_slice(&encoding[offset..(offset + 32)]).expect("coordinates are reduced");
+        let point = bn::AffineG2::new(bn::Fq2::new(read(32), read(0)), bn::Fq2::new(read(96), read(64)));
+        if point.is_err() {
+            return encoding;
+        }
+    }
+}
+
+// Elements of Fq2 = Fq[u] / (u^2 + 1) as (real, imaginary) coefficients
+type Fq2Element = (num_bigint::BigUint, num_bigint::BigUint);
+
+fn fq2_mul(a: &Fq2Element, b: &Fq2Element, modulus: &num_bigint::BigUint) -> Fq2Element {
+    // both products are below p^2
+    let real = (&a.0 * &b.0 + modulus * modulus - &a.1 * &b.1) % modulus;
+    let imaginary = (&a.0 * &b.1 + &a.1 * &b.0) % modulus;
+
+    (real, imaginary)
+}
+
+fn fq2_pow(a: &Fq2Element, exponent: &num_bigint::BigUint, modulus: &num_bigint::BigUint) -> Fq2Element {
+    use num_bigint::BigUint;
+
+    let mut result = (BigUint::from(1u64), BigUint::from(0u64));
+    for i in (0..exponent.bits()).rev() {
+        result = fq2_mul(&result, &result, modulus);
+        if (exponent >> i) & BigUint::from(1u64) == BigUint::from(1u64) {
+            result = fq2_mul(&result, a, modulus);
+        }
+    }
+
+    result
+}
+
+// Algorithm 9 of "Square root computation over even extension fields" by Adj and
+// Rodriguez-Henriquez, valid since p = 3 mod 4
+fn fq2_sqrt(a: &Fq2Element, modulus: &num_bigint::BigUint) -> Option<Fq2Element> {
+    use num_bigint::BigUint;
+
+    let minus_one = (modulus - BigUint::from(1u64), BigUint::from(0u64));
+
+    let a1 = fq2_pow(a, &((modulus - BigUint::from(3u64)) >> 2), modulus);
+    let alpha = fq2_mul(&a1, &fq2_mul(&a1, a, modulus), modulus);
+    // alpha^p is a conjugate of alpha
+    let alpha_conjugate = (alpha.0.clone(), (modulus - &alpha.1) % modulus);
+    if fq2_mul(&alpha_conjugate, &alpha, modulus) == minus_one {
+        return None;
+    }
+
+    let x0 = fq2_mul(&a1, a, modulus);
+    let root = if alpha == minus_one {
+        // multiplication by u
+        ((modulus - &x0.1) % modulus, x0.0)
+    } else {
+        let b = fq2_pow(&((&alpha.0 + BigUint::from(1u64)) % modulus, alpha.1.clone()), &((modulus - BigUint::from(1u64)) >> 1), modulus);
+        fq2_mul(&b, &x0, modulus)
+    };
+
+    if fq2_mul(&root, &root, modulus) == *a {
+        Some(root)
+    } else {
+        None
+    }
 }
\ No newline at end of file
@@ -5,6 +5,22 @@ pub mod helpers;
 pub mod measurements;
 pub mod generator;
 pub mod serialization;
+pub mod state_test;
+pub mod spool;
+pub mod environment;
+pub mod manifest;
+pub mod results;
+pub mod report;
+pub mod chart;
+pub mod calibration;
+pub mod comparison;
+pub mod compliance;
+pub mod search;
+pub mod determinism;
+pub mod fitting;
+pub mod backends;
+pub mod gas_targets;
+pub mod fuzzing;
 
 #[cfg(test)]
 mod test {
@@ -23,7 +39,7 @@ mod test {
     #[test]
     #[ignore]
     fn benchmark_ecrecover() {
-        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
 
         const RUNS_PER_WORK_UNIT: usize = 100000;
         const PARALLEL_WORKS: usize = 50;
@@ -128,7 +144,7 @@ mod test {
     #[test]
     #[ignore]
     fn benchmark_existing_pairing_precompile() {
-        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
 
         const RUNS_PER_WORK_UNIT: usize = 1000;
         const PARALLEL_WORKS: usize = 50;
@@ -254,7 +270,9 @@ mod test {
     #[test]
     #[ignore]
     fn benchmark_sha256_precompile() {
-        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let gas_per_second = crate::calibration::gas_rate_from_env().gas_per_second;
+
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
 
         const RUNS_PER_WORK_UNIT: usize = 100000;
 
@@ -324,16 +342,14 @@ mod test {
 
         pb.finish();
 
-        const GAS_PER_SECOND: u128 = 35_000_000;
-
-        println!("Using {} gas/second", GAS_PER_SECOND);
+        println!("Using {} gas/second", gas_per_second);
 
         let mut as_vec: Vec<_> = sums.into_iter().collect();
         as_vec.sort_by(|a, b| a.0.cmp(&b.0));
 
         for (k, v) in as_vec.into_iter() {
             let ns_average = (v as u128) / (RUNS_PER_WORK_UNIT as u128);
-            let gas_average = GAS_PER_SECOND * ns_average / 1_000_000_000u128;
+            let gas_average = gas_per_second * ns_average / 1_000_000_000u128;
             println!("Hashed {} bytes for {} gas", k, gas_average);
         }
     }
@@ -341,7 +357,9 @@ mod test {
     #[test]
     #[ignore]
     fn benchmark_ripemd160_precompile() {
-        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let gas_per_second = crate::calibration::gas_rate_from_env().gas_per_second;
+
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
 
         const RUNS_PER_WORK_UNIT: usize = 100000;
 
@@ -411,16 +429,14 @@ mod test {
 
         pb.finish();
 
-        const GAS_PER_SECOND: u128 = 35_000_000;
-
-        println!("Using {} gas/second", GAS_PER_SECOND);
+        println!("Using {} gas/second", gas_per_second);
 
         let mut as_vec: Vec<_> = sums.into_iter().collect();
         as_vec.sort_by(|a, b| a.0.cmp(&b.0));
 
         for (k, v) in as_vec.into_iter() {
             let ns_average = (v as u128) / (RUNS_PER_WORK_UNIT as u128);
-            let gas_average = GAS_PER_SECOND * ns_average / 1_000_000_000u128;
+            let gas_average = gas_per_second * ns_average / 1_000_000_000u128;
             println!("Hashed {} bytes for {} gas", k, gas_average);
         }
     }
@@ -428,7 +444,9 @@ mod test {
     #[test]
     #[ignore]
     fn benchmark_blake2f_precompile() {
-        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let gas_per_second = crate::calibration::gas_rate_from_env().gas_per_second;
+
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
 
         const RUNS_PER_WORK_UNIT: usize = 10000;
 
@@ -545,16 +563,14 @@ mod test {
 
         pb.finish();
 
-        const GAS_PER_SECOND: u128 = 35_000_000;
-
-        println!("Using {} gas/second", GAS_PER_SECOND);
+        println!("Using {} gas/second", gas_per_second);
 
         let mut as_vec: Vec<_> = sums.into_iter().collect();
         as_vec.sort_by(|a, b| a.0.cmp(&b.0));
 
         for (k, v) in as_vec.into_iter() {
             let ns_average = (v as u128) / (RUNS_PER_WORK_UNIT as u128);
-            let gas_average = GAS_PER_SECOND * ns_average / 1_000_000_000u128;
+            let gas_average = gas_per_second * ns_average / 1_000_000_000u128;
             println!("Hashed {} rounds for {} gas", k, gas_average);
         }
     }
@@ -562,7 +578,9 @@ mod test {
     #[test]
     #[ignore]
     fn benchmark_bn_add_precompile() {
-        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let gas_per_second = crate::calibration::gas_rate_from_env().gas_per_second;
+
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
 
         const RUNS_PER_WORK_UNIT: usize = 1000;
         const PARALLEL_WORKS: usize = 50;
@@ -667,19 +685,19 @@ mod test {
 
         pb.finish();
 
-        const GAS_PER_SECOND: u128 = 35_000_000;
-
-        println!("Using {} gas/second", GAS_PER_SECOND);
+        println!("Using {} gas/second", gas_per_second);
 
         let ns_average = (sum as u128) / (RUNS_PER_WORK_UNIT as u128) / (PARALLEL_WORKS as u128);
-        let gas_average = GAS_PER_SECOND * ns_average / 1_000_000_000u128;
+        let gas_average = gas_per_second * ns_average / 1_000_000_000u128;
         println!("BN_ADD used {} gas on average", gas_average);
     }
 
     #[test]
     #[ignore]
     fn benchmark_bn_mul_precompile() {
-        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let gas_per_second = crate::calibration::gas_rate_from_env().gas_per_second;
+
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
 
         const RUNS_PER_WORK_UNIT: usize = 1000;
         const PARALLEL_WORKS: usize = 50;
@@ -778,19 +796,19 @@ mod test {
 
         pb.finish();
 
-        const GAS_PER_SECOND: u128 = 35_000_000;
-
-        println!("Using {} gas/second", GAS_PER_SECOND);
+        println!("Using {} gas/second", gas_per_second);
 
         let ns_average = (sum as u128) / (RUNS_PER_WORK_UNIT as u128) / (PARALLEL_WORKS as u128);
-        let gas_average = GAS_PER_SECOND * ns_average / 1_000_000_000u128;
+        let gas_average = gas_per_second * ns_average / 1_000_000_000u128;
         println!("BN_ADD used {} gas on average", gas_average);
     }
 
     #[test]
     #[ignore]
     fn benchmark_bn_pairing_precompile() {
-        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let gas_per_second = crate::calibration::gas_rate_from_env().gas_per_second;
+
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
 
         const RUNS_PER_WORK_UNIT: usize = 10000;
 
@@ -945,21 +963,65 @@ mod test {
 
         pb.finish();
 
-        const GAS_PER_SECOND: u128 = 35_000_000;
-
-        println!("Using {} gas/second", GAS_PER_SECOND);
+        println!("Using {} gas/second", gas_per_second);
 
         for (k, v) in sums.into_iter() {
             let ns_average = (v as u128) / (RUNS_PER_WORK_UNIT as u128);
-            let gas_average = GAS_PER_SECOND * ns_average / 1_000_000_000u128;
+            let gas_average = gas_per_second * ns_average / 1_000_000_000u128;
             println!("Paired {} pairs for {} gas", k, gas_average);
         }
     }
 
+    // Cost of the explicit G2 subgroup check of `run_bn_pair` compared to a whole single pair check
+    #[test]
+    #[ignore]
+    fn benchmark_g2_subgroup_check() {
+        let gas_per_second = crate::calibration::gas_rate_from_env().gas_per_second;
+
+        use crate::measurements::{self, TimingStats};
+        use bn::Group;
+
+        const NUM_POINTS: usize = 100;
+        const RUNS_PER_POINT: usize = 100;
+
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
+
+        let mut check_samples = vec![];
+        let mut pairing_samples = vec![];
+        for _ in 0..NUM_POINTS {
+            let mut scalar_buffer = vec![0u8; 32];
+            rng.fill_bytes(&mut scalar_buffer);
+            scalar_buffer[0] = 0;
+            let point = bn::G2::one() * read_fr(&scalar_buffer).unwrap();
+
+            let runnable = || is_in_g2_subgroup(point);
+            let checker = |r: bool| r;
+            check_samples.extend(measurements::measure_samples_with_validity(&runnable, &checker, RUNS_PER_POINT, None));
+
+            let (input, output) = crate::input_generators::generate_bnpair_vector(1, &mut rng);
+            let runnable = || crate::runners::run_bn_pair(&input);
+            let checker = |r: Result<[u8; 32], crate::runners::PrecompileError>| r == Ok(output);
+            pairing_samples.extend(measurements::measure_samples_with_validity(&runnable, &checker, RUNS_PER_POINT, None));
+        }
+
+        let check = TimingStats::from_samples(&check_samples);
+        let pairing = TimingStats::from_samples(&pairing_samples);
+
+        println!("G2 subgroup check: median {} ns, mean {} ns, {} gas",
+            check.median_ns, check.mean_ns, crate::generator::gas_for_ns(check.mean_ns, gas_per_second));
+        println!("Single pair check including the subgroup check: median {} ns, mean {} ns, {} gas",
+            pairing.median_ns, pairing.mean_ns, crate::generator::gas_for_ns(pairing.mean_ns, gas_per_second));
+        if pairing.mean_ns != 0 {
+            println!("Subgroup check is {:.1}% of a single pair check", (check.mean_ns as f64) / (pairing.mean_ns as f64) * 100f64);
+        }
+    }
+
     #[test]
     #[ignore]
     fn benchmark_keccak_sponge_price() {
-        let mut rng = XorShiftRng::from_seed([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let gas_per_second = crate::calibration::gas_rate_from_env().gas_per_second;
+
+        let mut rng = XorShiftRng::from_seed(crate::generator::rng_seed_from_env());
 
         const RUNS_PER_WORK_UNIT: usize = 10000;
 
@@ -1029,16 +1091,14 @@ mod test {
 
         pb.finish();
 
-        const GAS_PER_SECOND: u128 = 35_000_000;
-
-        println!("Using {} gas/second", GAS_PER_SECOND);
+        println!("Using {} gas/second", gas_per_second);
 
         let mut as_vec: Vec<_> = sums.into_iter().collect();
         as_vec.sort_by(|a, b| a.0.cmp(&b.0));
 
         for (k, v) in as_vec.into_iter() {
             let ns_average = (v as u128) / (RUNS_PER_WORK_UNIT as u128);
-            let gas_average = GAS_PER_SECOND * ns_average / 1_000_000_000u128;
+            let gas_average = gas_per_second * ns_average / 1_000_000_000u128;
             println!("Hashed {} bytes for {} gas", k, gas_average);
         }
     }
@@ -1111,16 +1171,16 @@ mod test {
 
     //     pb.finish();
 
-    //     const GAS_PER_SECOND: u128 = 35_000_000;
+    //     let gas_per_second = crate::calibration::gas_rate_from_env().gas_per_second;
 
-    //     println!("Using {} gas/second", GAS_PER_SECOND);
+    //     println!("Using {} gas/second", gas_per_second);
 
     //     let mut as_vec: Vec<_> = sums.into_iter().collect();
     //     as_vec.sort_by(|a, b| a.0.cmp(&b.0));
 
     //     for (k, v) in as_vec.into_iter() {
     //         let ns_average = (v as u128) / (RUNS_PER_WORK_UNIT as u128);
-    //         let gas_average = GAS_PER_SECOND * ns_average / 1_000_000_000u128;
+    //         let gas_average = gas_per_second * ns_average / 1_000_000_000u128;
     //         println!("Hashed {} bytes for {} gas", k, gas_average);
     //     }
     // }
@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Serialize, Deserialize};
+
+use super::environment::{self, Environment};
+use super::generator::RunParameters;
+use super::{helpers, runners};
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Manifest {
+    pub precompile: String,
+    pub parameters: RunParameters,
+    pub schedules: Vec<String>,
+    pub environment: Environment,
+    pub files: Vec<ManifestEntry>
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub sha256: String,
+    pub records: Option<usize>,
+    pub scalar: Option<u64>,
+    pub gas: Option<u64>
+}
+
+// Modification times come from a coarser clock than `SystemTime::now()` and may lag behind it
+const MODIFICATION_TIME_SLACK: Duration = Duration::from_secs(1);
+
+// Describes every file under `base_path` written since `run_started` (except the manifest itself
+// and hidden files), so that files left by an earlier run, e.g. of other lengths, are not described
+pub fn write_manifest(base_path: &str, precompile: &str, parameters: &RunParameters, schedules: &[&str], run_started: SystemTime) -> std::io::Result<()> {
+    let manifest = Manifest {
+        precompile: precompile.to_string(),
+        parameters: parameters.clone(),
+        schedules: schedules.iter().map(|s| s.to_string()).collect(),
+        environment: environment::current_environment(),
+        files: describe_files(base_path, Some(run_started))?
+    };
+
+    let path = format!("{}/{}", base_path, MANIFEST_FILE_NAME);
+    helpers::write_atomically(&path, |file| {
+        serde_json::to_writer_pretty(file, &manifest)?;
+
+        Ok(())
+    })
+}
+
+pub fn read_manifest(base_path: &str) -> std::io::Result<Manifest> {
+    let file = std::fs::File::open(format!("{}/{}", base_path, MANIFEST_FILE_NAME))?;
+    let manifest = serde_json::from_reader(std::io::BufReader::new(file))?;
+
+    Ok(manifest)
+}
+
+// Returns a list of human readable mismatches between the manifest and files on disk, including
+// files left by earlier runs
+pub fn verify_manifest(base_path: &str) -> std::io::Result<Vec<String>> {
+    let manifest = read_manifest(base_path)?;
+    let actual = describe_files(base_path, None)?;
+
+    let mut problems = vec![];
+    for expected in manifest.files.iter() {
+        match actual.iter().find(|a| a.path == expected.path) {
+            None => problems.push(format!("{}: file is missing", expected.path)),
+            Some(a) => {
+                if a.sha256 != expected.sha256 {
+                    problems.push(format!("{}: sha256 is {}, expected {}", a.path, a.sha256, expected.sha256));
+                }
+                if a.records != expected.records {
+                    problems.push(format!("{}: contains {:?} records, expected {:?}", a.path, a.records, expected.records));
+                }
+            }
+        }
+    }
+
+    for a in actual.iter() {
+        if !manifest.files.iter().any(|expected| expected.path == a.path) {
+            problems.push(format!("{}: file is not listed in the manifest", a.path));
+        }
+    }
+
+    Ok(problems)
+}
+
+fn describe_files(base_path: &str, written_since: Option<SystemTime>) -> std::io::Result<Vec<ManifestEntry>> {
+    let base = Path::new(base_path);
+    let mut paths = vec![];
+    collect_files(base, &mut paths)?;
+    paths.sort();
+
+    if let Some(since) = written_since {
+        let since = since.checked_sub(MODIFICATION_TIME_SLACK).unwrap_or(since);
+        let mut written = vec![];
+        for path in paths.into_iter() {
+            if std::fs::metadata(&path)?.modified()? >= since {
+                written.push(path);
+            }
+        }
+        paths = written;
+    }
+
+    let mut entries = vec![];
+    for path in paths.into_iter() {
+        let relative = path.strip_prefix(base)
+            .expect("collected files are under the base path")
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if relative == MANIFEST_FILE_NAME {
+            continue;
+        }
+
+        let contents = std::fs::read(&path)?;
+        let (scalar, gas) = parse_scalar_and_gas(&relative);
+
+        entries.push(ManifestEntry {
+            sha256: hex::encode(runners::run_sha256(&contents)),
+            records: count_records(&relative, &contents),
+            path: relative,
+            scalar,
+            gas
+        });
+    }
+
+    Ok(entries)
+}
+
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn count_records(path: &str, contents: &[u8]) -> Option<usize> {
+    if path.ends_with(".csv") {
+        let reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(contents);
+
+        Some(reader.into_records().count())
+    } else if path.ends_with(".json") {
+        match serde_json::from_slice::<serde_json::Value>(contents).ok()? {
+            serde_json::Value::Array(records) => Some(records.len()),
+            // state test fillers are objects of tests, each having a list of transaction inputs
+            serde_json::Value::Object(tests) => {
+                Some(tests.values().map(|t| {
+                    t["transaction"]["data"].as_array().map(|d| d.len()).unwrap_or(1)
+                }).sum())
+            },
+            _ => None
+        }
+    } else {
+        None
+    }
+}
+
+// Vector files are named as `input_param_scalar_{scalar}_gas_{gas}.csv`
+pub fn parse_scalar_and_gas(path: &str) -> (Option<u64>, Option<u64>) {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let stem = match file_name.strip_prefix("input_param_scalar_").and_then(|s| s.strip_suffix(".csv")) {
+        Some(stem) => stem,
+        None => return (None, None)
+    };
+
+    let mut parts = stem.splitn(2, "_gas_");
+    let scalar = parts.next().and_then(|s| s.parse().ok());
+    let gas = parts.next().and_then(|s| s.parse().ok());
+
+    (scalar, gas)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn verify_vector_manifests() {
+        let vectors_path = std::env::var("BENCH_VECTORS_PATH").unwrap_or_else(|_| String::from("./vectors"));
+
+        let mut dirs: Vec<_> = std::fs::read_dir(&vectors_path).unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.is_dir())
+            .collect();
+        dirs.sort();
+
+        let mut all_problems = vec![];
+        for dir in dirs.into_iter() {
+            let dir = dir.to_string_lossy().into_owned();
+            if !Path::new(&dir).join(MANIFEST_FILE_NAME).exists() {
+                println!("{}: no manifest, skipping", dir);
+                continue;
+            }
+            let problems = verify_manifest(&dir).unwrap();
+            println!("{}: {} problem(s)", dir, problems.len());
+            for p in problems.into_iter() {
+                all_problems.push(format!("{}/{}", dir, p));
+            }
+        }
+
+        for p in all_problems.iter() {
+            println!("{}", p);
+        }
+
+        assert!(all_problems.is_empty(), "vectors do not match their manifests");
+    }
+}
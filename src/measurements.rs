@@ -1,3 +1,5 @@
+use serde::{Serialize, Deserialize};
+
 pub fn measure<F: Fn() -> Result<(), ()>>(runnable: &F, num_attempts: usize) -> u128 {
     use std::time::Instant;
     
@@ -27,4 +29,96 @@ pub fn measure_with_validity<T, F: Fn() -> T, C: Fn(T) -> bool>(runnable: &F, ch
     }
 
     total
-} 
\ No newline at end of file
+} 
+
+const TIMER_CALIBRATION_SAMPLES: usize = 10000;
+
+// Median time of taking an `Instant` and reading its elapsed time, measured once per process.
+// It is comparable to the running time of precompiles taking less than a microsecond
+pub fn timer_overhead_ns() -> u128 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static OVERHEAD: OnceLock<u128> = OnceLock::new();
+
+    *OVERHEAD.get_or_init(|| {
+        let mut samples: Vec<u128> = (0..TIMER_CALIBRATION_SAMPLES).map(|_| {
+            let start = Instant::now();
+            start.elapsed().as_nanos()
+        }).collect();
+        samples.sort_unstable();
+
+        samples[samples.len() / 2]
+    })
+}
+
+// Timer overhead is subtracted from every sample. With a budget, stops early once the samples
+// add up to `budget_ns`, but takes at least one sample
+pub fn measure_samples_with_validity<T, F: Fn() -> T, C: Fn(T) -> bool>(runnable: &F, checker: &C, num_attempts: usize, budget_ns: Option<u128>) -> Vec<u128> {
+    use std::time::Instant;
+
+    let overhead_ns = timer_overhead_ns();
+    let mut samples = Vec::with_capacity(num_attempts);
+    let mut total = 0u128;
+    for _ in 0..num_attempts {
+        let start = Instant::now();
+        let r = runnable();
+        let elapsed_nanos = start.elapsed().as_nanos().saturating_sub(overhead_ns);
+        let valid = checker(r);
+        assert!(valid);
+        samples.push(elapsed_nanos);
+        total += elapsed_nanos;
+        if budget_ns.map(|b| total >= b).unwrap_or(false) {
+            break;
+        }
+    }
+
+    samples
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct TimingStats {
+    pub samples: usize,
+    pub median_ns: u128,
+    pub mean_ns: u128,
+    pub stddev_ns: f64
+}
+
+impl TimingStats {
+    pub fn from_samples(samples: &[u128]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let mid = sorted.len() / 2;
+        let median_ns = if sorted.len() % 2 == 0 {
+            (sorted[mid - 1] + sorted[mid]) / 2
+        } else {
+            sorted[mid]
+        };
+
+        let total: u128 = samples.iter().sum();
+        let mean_ns = total / (samples.len() as u128);
+
+        let stddev_ns = if samples.len() > 1 {
+            let mean = (total as f64) / (samples.len() as f64);
+            let sum_of_squares: f64 = samples.iter().map(|&s| {
+                let d = (s as f64) - mean;
+                d * d
+            }).sum();
+
+            (sum_of_squares / ((samples.len() - 1) as f64)).sqrt()
+        } else {
+            0f64
+        };
+
+        Self {
+            samples: samples.len(),
+            median_ns,
+            mean_ns,
+            stddev_ns
+        }
+    }
+}
@@ -45,6 +45,42 @@ impl Pricer {
             }
         }
     }
+
+    // Largest parameter among ones priced closest to `target_gas` (below the target on a tie),
+    // constant prices don't depend on the parameter and can't be inverted
+    pub fn closest_scalar(&self, target_gas: u64) -> Option<u64> {
+        let inner = match self {
+            Pricer::Constant(_) => {
+                return None;
+            },
+            Pricer::Linear(inner) => inner
+        };
+
+        let min_chunks = if inner.use_ceil_div {
+            ceil_div(inner.scalar_shift, inner.scalar_chunk_size)
+        } else {
+            floor_div(inner.scalar_shift, inner.scalar_chunk_size)
+        };
+
+        let variable_gas = target_gas.saturating_sub(inner.constant);
+        let below = floor_div(variable_gas, inner.per_chunk);
+        let above = ceil_div(variable_gas, inner.per_chunk);
+        let chunks = if above * inner.per_chunk - variable_gas < variable_gas - below * inner.per_chunk {
+            above
+        } else {
+            below
+        };
+        let chunks = std::cmp::max(chunks, min_chunks);
+
+        // largest scalar that still fits into `chunks`
+        let scalar = if inner.use_ceil_div {
+            chunks * inner.scalar_chunk_size - inner.scalar_shift
+        } else {
+            chunks * inner.scalar_chunk_size + inner.scalar_chunk_size - 1 - inner.scalar_shift
+        };
+
+        Some(scalar)
+    }
 }
 
 pub fn current_sha256_pricer() -> Pricer {
@@ -140,6 +176,14 @@ pub fn bnpair_pricer() -> Pricer {
     Pricer::Linear(l)
 }
 
+pub fn ecrecover_pricer() -> Pricer {
+    let c = ConstantPricer {
+        constant: 3000
+    };
+
+    Pricer::Constant(c)
+}
+
 pub fn blake2f_pricer() -> Pricer {
     let l = LinearPricer {
         constant: 0,
@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+
+use super::{chart, environment};
+use super::helpers;
+use super::results::{self, MeasurementResults};
+
+pub const MARKDOWN_REPORT_FILE_NAME: &str = "report.md";
+pub const HTML_REPORT_FILE_NAME: &str = "report.html";
+
+// Measurements of all vectors sharing the same family and scalar parameter
+#[derive(Clone, Debug)]
+pub struct ScalarSummary {
+    pub family: String,
+    pub scalar: u64,
+    pub num_vectors: usize,
+    pub median_ns: u128,
+    pub mean_ns: u128,
+    pub max_mean_ns: u128,
+    // pooled over vectors, i.e. square root of the mean variance
+    pub stddev_ns: f64,
+    pub gas: u64,
+    pub schedule_gas: BTreeMap<String, u64>
+}
+
+impl ScalarSummary {
+    // e.g. `0` for the default family and `0 (doubling)` for others
+    pub fn param(&self) -> String {
+        if self.family.is_empty() {
+            self.scalar.to_string()
+        } else {
+            format!("{} ({})", self.scalar, self.family)
+        }
+    }
+
+    // Positive headroom means that schedule charges more than implied by measurements
+    pub fn headroom_percent(&self, schedule: &str) -> Option<f64> {
+        let schedule_gas = *self.schedule_gas.get(schedule)?;
+        if schedule_gas == 0 {
+            return None;
+        }
+
+        Some(((schedule_gas as f64) - (self.gas as f64)) / (schedule_gas as f64) * 100f64)
+    }
+
+    pub fn fits(&self, schedule: &str) -> Option<bool> {
+        self.schedule_gas.get(schedule).map(|&g| self.gas <= g)
+    }
+}
+
+// Rejected inputs are left out, see `summarize_rejections`
+pub fn summarize(results: &MeasurementResults) -> Vec<ScalarSummary> {
+    let mut per_scalar: BTreeMap<(String, u64), Vec<&results::TimingRecord>> = BTreeMap::new();
+    for r in results.records.iter().filter(|r| r.expected_error.is_none()) {
+        per_scalar.entry((r.family.clone(), r.scalar)).or_default().push(r);
+    }
+
+    per_scalar.into_iter().map(|((family, scalar), records)| {
+        let num_vectors = records.len() as u128;
+        let mean_ns = records.iter().map(|r| r.mean_ns).sum::<u128>() / num_vectors;
+        let median_ns = records.iter().map(|r| r.median_ns).sum::<u128>() / num_vectors;
+        let max_mean_ns = records.iter().map(|r| r.mean_ns).max().unwrap_or(0);
+        let stddev_ns = (records.iter().map(|r| r.stddev_ns * r.stddev_ns).sum::<f64>() / (records.len() as f64)).sqrt();
+
+        ScalarSummary {
+            family,
+            scalar,
+            num_vectors: records.len(),
+            median_ns,
+            mean_ns,
+            max_mean_ns,
+            stddev_ns,
+            gas: (mean_ns * results.gas_per_second / 1_000_000_000) as u64,
+            schedule_gas: records[0].schedule_gas.clone()
+        }
+    }).collect()
+}
+
+// Measurements of invalid vectors sharing the same family, scalar parameter and expected error.
+// Rejected calls consume all the gas given to them, so a rejection must not take longer than
+// the scheduled price of the input
+#[derive(Clone, Debug)]
+pub struct RejectionSummary {
+    pub family: String,
+    pub scalar: u64,
+    pub expected_error: String,
+    pub num_vectors: usize,
+    pub mean_ns: u128,
+    pub max_mean_ns: u128,
+    pub gas: u64,
+    pub schedule_gas: BTreeMap<String, u64>
+}
+
+impl RejectionSummary {
+    // Implied gas as a share of the gas charged by a schedule
+    pub fn charged_share_percent(&self, schedule: &str) -> Option<f64> {
+        let schedule_gas = *self.schedule_gas.get(schedule)?;
+        if schedule_gas == 0 {
+            return None;
+        }
+
+        Some((self.gas as f64) / (schedule_gas as f64) * 100f64)
+    }
+}
+
+pub fn summarize_rejections(results: &MeasurementResults) -> Vec<RejectionSummary> {
+    let mut per_error: BTreeMap<(String, u64, String), Vec<&results::TimingRecord>> = BTreeMap::new();
+    for r in results.records.iter() {
+        if let Some(e) = r.expected_error.as_ref() {
+            per_error.entry((r.family.clone(), r.scalar, e.clone())).or_default().push(r);
+        }
+    }
+
+    per_error.into_iter().map(|((family, scalar, expected_error), records)| {
+        let mean_ns = records.iter().map(|r| r.mean_ns).sum::<u128>() / (records.len() as u128);
+
+        RejectionSummary {
+            family,
+            scalar,
+            expected_error,
+            num_vectors: records.len(),
+            mean_ns,
+            max_mean_ns: records.iter().map(|r| r.mean_ns).max().unwrap_or(0),
+            gas: (mean_ns * results.gas_per_second / 1_000_000_000) as u64,
+            schedule_gas: records[0].schedule_gas.clone()
+        }
+    }).collect()
+}
+
+// One line verdict per schedule, e.g. "fits for all 33 parameters" or "exceeds for 2 of 33 parameters (worst headroom -12.5%)"
+pub fn verdicts(summaries: &[ScalarSummary], schedules: &[String]) -> Vec<(String, bool, String)> {
+    schedules.iter().map(|schedule| {
+        let exceeding: Vec<_> = summaries.iter()
+            .filter(|s| s.fits(schedule) == Some(false))
+            .collect();
+        let worst = summaries.iter()
+            .filter_map(|s| s.headroom_percent(schedule))
+            .fold(None, |acc: Option<f64>, h| Some(acc.map_or(h, |a| a.min(h))));
+        let worst = worst.map(|w| format!("{:.1}%", w)).unwrap_or_else(|| String::from("n/a"));
+
+        if exceeding.is_empty() {
+            (schedule.clone(), true, format!("fits for all {} parameters (worst headroom {})", summaries.len(), worst))
+        } else {
+            let params: Vec<String> = exceeding.iter().map(|s| s.param()).collect();
+            (schedule.clone(), false, format!("exceeds for {} of {} parameters (worst headroom {}): {}",
+                exceeding.len(), summaries.len(), worst, params.join(", ")))
+        }
+    }).collect()
+}
+
+pub fn render_markdown(all_results: &[MeasurementResults]) -> String {
+    let mut out = String::new();
+    out.push_str("# Precompile schedule fit report\n\n");
+
+    for results in all_results.iter() {
+        let summaries = summarize(results);
+        let schedules = results::schedule_names(&results.records);
+
+        out.push_str(&format!("## {}\n\n", results.precompile));
+        out.push_str(&format!("Environment: {}\n\n", environment::describe(&results.environment)));
+        out.push_str(&format!("Gas is implied from the mean running time at {} gas/second.\n\n", results.gas_per_second));
+
+        if chart_for(results).is_some() {
+            out.push_str(&format!("![{}]({})\n\n", results.precompile, chart_file_name(&results.precompile)));
+        }
+
+        for (schedule, fits, verdict) in verdicts(&summaries, &schedules).into_iter() {
+            out.push_str(&format!("- **{}**: {} {}\n", schedule, if fits { "PASS" } else { "FAIL" }, verdict));
+        }
+        out.push('\n');
+
+        let mut header = vec![String::from("param"), String::from("vectors"), String::from("median ns"), String::from("mean ns"), String::from("max ns"), String::from("implied gas")];
+        for s in schedules.iter() {
+            header.push(format!("{} gas", s));
+            header.push(format!("{} headroom", s));
+        }
+        out.push_str(&format!("| {} |\n", header.join(" | ")));
+        out.push_str(&format!("|{}\n", "---:|".repeat(header.len())));
+
+        for row in table_rows(&summaries, &schedules).into_iter() {
+            out.push_str(&format!("| {} |\n", row.join(" | ")));
+        }
+        out.push('\n');
+
+        let rejections = summarize_rejections(results);
+        if !rejections.is_empty() {
+            out.push_str("### Rejection cost\n\n");
+            out.push_str(&format!("| {} |\n", rejection_header(&schedules).join(" | ")));
+            out.push_str(&format!("|{}\n", "---:|".repeat(rejection_header(&schedules).len())));
+            for row in rejection_rows(&rejections, &schedules).into_iter() {
+                out.push_str(&format!("| {} |\n", row.join(" | ")));
+            }
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+pub fn render_html(all_results: &[MeasurementResults]) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Precompile schedule fit report</title>\n");
+    out.push_str("<style>\n");
+    out.push_str("body { font-family: sans-serif; margin: 2em; }\n");
+    out.push_str("table { border-collapse: collapse; margin-bottom: 2em; }\n");
+    out.push_str("th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: right; }\n");
+    out.push_str("th { background: #eee; }\n");
+    out.push_str(".pass { color: #1a7f37; font-weight: bold; }\n");
+    out.push_str(".fail { color: #cf222e; font-weight: bold; }\n");
+    out.push_str("td.fail { background: #ffebe9; }\n");
+    out.push_str("</style>\n</head>\n<body>\n");
+    out.push_str("<h1>Precompile schedule fit report</h1>\n");
+
+    for results in all_results.iter() {
+        let summaries = summarize(results);
+        let schedules = results::schedule_names(&results.records);
+
+        out.push_str(&format!("<h2>{}</h2>\n", escape_html(&results.precompile)));
+        out.push_str(&format!("<p>Environment: {}</p>\n", escape_html(&environment::describe(&results.environment))));
+        out.push_str(&format!("<p>Gas is implied from the mean running time at {} gas/second.</p>\n", results.gas_per_second));
+
+        out.push_str("<ul>\n");
+        for (schedule, fits, verdict) in verdicts(&summaries, &schedules).into_iter() {
+            out.push_str(&format!("<li><b>{}</b>: <span class=\"{}\">{}</span> {}</li>\n",
+                escape_html(&schedule),
+                if fits { "pass" } else { "fail" },
+                if fits { "PASS" } else { "FAIL" },
+                escape_html(&verdict)
+            ));
+        }
+        out.push_str("</ul>\n");
+
+        if let Some(svg) = chart_for(results) {
+            out.push_str(&svg);
+        }
+
+        out.push_str("<table>\n<tr><th>param</th><th>vectors</th><th>median ns</th><th>mean ns</th><th>max ns</th><th>implied gas</th>");
+        for s in schedules.iter() {
+            out.push_str(&format!("<th>{} gas</th><th>{} headroom</th>", escape_html(s), escape_html(s)));
+        }
+        out.push_str("</tr>\n");
+
+        for (summary, row) in summaries.iter().zip(table_rows(&summaries, &schedules)) {
+            out.push_str("<tr>");
+            for (i, cell) in row.iter().enumerate() {
+                // schedule columns go in pairs after the six measurement columns
+                let class = if i >= 6 && summary.fits(&schedules[(i - 6) / 2]) == Some(false) { " class=\"fail\"" } else { "" };
+                out.push_str(&format!("<td{}>{}</td>", class, escape_html(cell)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+
+        let rejections = summarize_rejections(results);
+        if !rejections.is_empty() {
+            out.push_str("<h3>Rejection cost</h3>\n<table>\n<tr>");
+            for h in rejection_header(&schedules).iter() {
+                out.push_str(&format!("<th>{}</th>", escape_html(h)));
+            }
+            out.push_str("</tr>\n");
+            for row in rejection_rows(&rejections, &schedules).into_iter() {
+                out.push_str("<tr>");
+                for cell in row.iter() {
+                    out.push_str(&format!("<td>{}</td>", escape_html(cell)));
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</table>\n");
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+
+    out
+}
+
+// Reads results of every precompile under `vectors_path` and writes both reports into `output_path`
+pub fn write_report(vectors_path: &str, output_path: &str) -> std::io::Result<usize> {
+    let all_results = results::find_results(vectors_path)?;
+
+    let markdown = render_markdown(&all_results);
+    helpers::write_atomically(&format!("{}/{}", output_path, MARKDOWN_REPORT_FILE_NAME), |file| {
+        use std::io::Write;
+
+        file.write_all(markdown.as_bytes())
+    })?;
+
+    let html = render_html(&all_results);
+    helpers::write_atomically(&format!("{}/{}", output_path, HTML_REPORT_FILE_NAME), |file| {
+        use std::io::Write;
+
+        file.write_all(html.as_bytes())
+    })?;
+
+    for results in all_results.iter() {
+        if let Some(svg) = chart_for(results) {
+            helpers::write_atomically(&format!("{}/{}", output_path, chart_file_name(&results.precompile)), |file| {
+                use std::io::Write;
+
+                file.write_all(svg.as_bytes())
+            })?;
+        }
+    }
+
+    Ok(all_results.len())
+}
+
+// Only the default family is charted, special cases would not form a curve
+pub fn chart_for(results: &MeasurementResults) -> Option<String> {
+    let summaries: Vec<_> = summarize(results).into_iter().filter(|s| s.family.is_empty()).collect();
+    let schedules = results::schedule_names(&results.records);
+
+    chart::render_svg(&results.precompile, &summaries, &schedules, results.gas_per_second)
+}
+
+fn chart_file_name(precompile: &str) -> String {
+    format!("{}.svg", precompile)
+}
+
+fn table_rows(summaries: &[ScalarSummary], schedules: &[String]) -> Vec<Vec<String>> {
+    summaries.iter().map(|s| {
+        let mut row = vec![
+            s.param(),
+            s.num_vectors.to_string(),
+            s.median_ns.to_string(),
+            s.mean_ns.to_string(),
+            s.max_mean_ns.to_string(),
+            s.gas.to_string()
+        ];
+        for schedule in schedules.iter() {
+            row.push(s.schedule_gas.get(schedule).map(|g| g.to_string()).unwrap_or_default());
+            row.push(s.headroom_percent(schedule).map(|h| format!("{:.1}%", h)).unwrap_or_default());
+        }
+
+        row
+    }).collect()
+}
+
+fn rejection_header(schedules: &[String]) -> Vec<String> {
+    let mut header = vec![String::from("param"), String::from("expected error"), String::from("vectors"), String::from("mean ns"), String::from("max ns"), String::from("implied gas")];
+    for s in schedules.iter() {
+        header.push(format!("{} gas", s));
+        header.push(format!("{} share", s));
+    }
+
+    header
+}
+
+fn rejection_rows(rejections: &[RejectionSummary], schedules: &[String]) -> Vec<Vec<String>> {
+    rejections.iter().map(|r| {
+        let mut row = vec![
+            format!("{} ({})", r.scalar, r.family),
+            r.expected_error.clone(),
+            r.num_vectors.to_string(),
+            r.mean_ns.to_string(),
+            r.max_mean_ns.to_string(),
+            r.gas.to_string()
+        ];
+        for schedule in schedules.iter() {
+            row.push(r.schedule_gas.get(schedule).map(|g| g.to_string()).unwrap_or_default());
+            row.push(r.charged_share_percent(schedule).map(|p| format!("{:.1}%", p)).unwrap_or_default());
+        }
+
+        row
+    }).collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn generate_report() {
+        let vectors_path = std::env::var("BENCH_VECTORS_PATH").unwrap_or_else(|_| String::from("./vectors"));
+        let report_path = std::env::var("BENCH_REPORT_PATH").unwrap_or_else(|_| vectors_path.clone());
+
+        let num_precompiles = write_report(&vectors_path, &report_path).unwrap();
+        println!("Report for {} precompile(s) is written into {}", num_precompiles, report_path);
+    }
+}
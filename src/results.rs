@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use super::environment::Environment;
+use super::helpers;
+
+pub const TIMINGS_JSON_FILE_NAME: &str = "timings.json";
+pub const TIMINGS_CSV_FILE_NAME: &str = "timings.csv";
+
+// Timing of a single vector, `gas` is derived from the mean time at `gas_per_second` of the run
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TimingRecord {
+    pub precompile: String,
+    // empty for the default family of random vectors
+    #[serde(default)]
+    pub family: String,
+    pub scalar: u64,
+    // test name of the vector in JSON files and state tests, e.g. `sha256_64_slowest_0`
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub label: String,
+    // error of an invalid input the precompile must reject
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_error: Option<String>,
+    // hex encoded seed the vector was generated from
+    #[serde(default)]
+    pub seed: String,
+    pub samples: usize,
+    pub median_ns: u128,
+    pub mean_ns: u128,
+    pub stddev_ns: f64,
+    pub gas: u64,
+    pub schedule_gas: BTreeMap<String, u64>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MeasurementResults {
+    pub precompile: String,
+    pub gas_per_second: u128,
+    pub environment: Environment,
+    pub records: Vec<TimingRecord>
+}
+
+pub fn write_results(base_path: &str, results: &MeasurementResults) -> std::io::Result<()> {
+    let json_path = format!("{}/{}", base_path, TIMINGS_JSON_FILE_NAME);
+    helpers::write_atomically(&json_path, |file| {
+        serde_json::to_writer_pretty(file, results)?;
+
+        Ok(())
+    })?;
+
+    let schedules = schedule_names(&results.records);
+
+    let csv_path = format!("{}/{}", base_path, TIMINGS_CSV_FILE_NAME);
+    helpers::write_atomically(&csv_path, |file| {
+        let mut writer = csv::Writer::from_writer(file);
+
+        let mut header: Vec<String> = vec!["precompile", "family", "scalar", "name", "label", "expected_error", "seed", "samples", "median_ns", "mean_ns", "stddev_ns", "gas"]
+            .into_iter()
+            .map(|h| h.to_string())
+            .collect();
+        header.extend(schedules.iter().map(|s| format!("gas_{}", s)));
+        writer.write_record(&header)?;
+
+        for r in results.records.iter() {
+            let mut row = vec![
+                r.precompile.clone(),
+                r.family.clone(),
+                r.scalar.to_string(),
+                r.name.clone(),
+                r.label.clone(),
+                r.expected_error.clone().unwrap_or_default(),
+                r.seed.clone(),
+                r.samples.to_string(),
+                r.median_ns.to_string(),
+                r.mean_ns.to_string(),
+                format!("{:.1}", r.stddev_ns),
+                r.gas.to_string()
+            ];
+            row.extend(schedules.iter().map(|s| {
+                r.schedule_gas.get(s).map(|g| g.to_string()).unwrap_or_default()
+            }));
+            writer.write_record(&row)?;
+        }
+
+        writer.flush()
+    })
+}
+
+// `path` is either a results file or a directory containing one
+pub fn read_results(path: &str) -> std::io::Result<MeasurementResults> {
+    let path = std::path::Path::new(path);
+    let path = if path.is_dir() {
+        path.join(TIMINGS_JSON_FILE_NAME)
+    } else {
+        path.to_path_buf()
+    };
+
+    let file = std::fs::File::open(&path)?;
+    let results = serde_json::from_reader(std::io::BufReader::new(file))?;
+
+    Ok(results)
+}
+
+// Reads results of every precompile stored in direct subfolders of `root`, e.g. `./vectors`
+pub fn find_results(root: &str) -> std::io::Result<Vec<MeasurementResults>> {
+    let mut dirs = vec![];
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.join(TIMINGS_JSON_FILE_NAME).exists() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+
+    let mut all_results = vec![];
+    for dir in dirs.into_iter() {
+        all_results.push(read_results(&dir.to_string_lossy())?);
+    }
+
+    Ok(all_results)
+}
+
+pub fn schedule_names(records: &[TimingRecord]) -> Vec<String> {
+    let mut names: Vec<String> = records.iter()
+        .flat_map(|r| r.schedule_gas.keys().cloned())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    names
+}
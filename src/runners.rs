@@ -1,5 +1,41 @@
 use super::helpers;
 
+// Reason an input is rejected, with a message that says which part of the input is invalid
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PrecompileError {
+    InvalidLength(&'static str),
+    InvalidPairingLength(&'static str),
+    InvalidFlag(&'static str),
+    // field element or scalar not below the modulus
+    NotBelowModulus(&'static str),
+    NotOnCurve(&'static str),
+    NotInSubgroup(&'static str)
+}
+
+impl PrecompileError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            PrecompileError::InvalidLength(m) => m,
+            PrecompileError::InvalidPairingLength(m) => m,
+            PrecompileError::InvalidFlag(m) => m,
+            PrecompileError::NotBelowModulus(m) => m,
+            PrecompileError::NotOnCurve(m) => m,
+            PrecompileError::NotInSubgroup(m) => m
+        }
+    }
+
+    // Error go-ethereum rejects the input with, compared verbatim by its precompile failure tests
+    pub fn geth_error(&self) -> &'static str {
+        match self {
+            PrecompileError::InvalidLength(_) => "invalid input length",
+            PrecompileError::InvalidPairingLength(_) => "bad elliptic curve pairing size",
+            PrecompileError::InvalidFlag(_) => "invalid final flag",
+            PrecompileError::NotBelowModulus(_) => "bn256: coordinate exceeds modulus",
+            PrecompileError::NotOnCurve(_) | PrecompileError::NotInSubgroup(_) => "bn256: malformed point"
+        }
+    }
+}
+
 pub fn run_sha256(input: &[u8]) -> [u8; 32] {
     use parity_crypto::digest;
     use std::io::Write;
@@ -22,17 +58,58 @@ pub fn run_ripemd160(input: &[u8]) -> [u8; 20] {
     output
 }
 
-pub fn run_blake2f(input: &[u8]) -> [u8; 64] {
+// Input is `hash || v || r || s`, invalid signatures produce an all-zero output
+pub fn run_ecrecover(input: &[u8]) -> [u8; 32] {
+    use parity_crypto::publickey::{Signature, recover as ec_recover};
+    use ethereum_types::{H256};
+    use keccak_hash::keccak;
+
+    let len = std::cmp::min(input.len(), 128);
+
+    let mut padded = [0u8; 128];
+    padded[..len].copy_from_slice(&input[..len]);
+
+    let hash = H256::from_slice(&padded[0..32]);
+    let v = H256::from_slice(&padded[32..64]);
+    let r = H256::from_slice(&padded[64..96]);
+    let s = H256::from_slice(&padded[96..128]);
+
+    let mut output = [0u8; 32];
+
+    let bit = match v[31] {
+        27 | 28 if v.0[..31] == [0; 31] => v[31] - 27,
+        _ => { return output; },
+    };
+
+    let s = Signature::from_rsv(&r, &s, bit);
+    if s.is_valid() {
+        if let Ok(p) = ec_recover(&s, &hash) {
+            let r = keccak(p);
+            output[12..].copy_from_slice(&r.as_bytes()[12..]);
+        }
+    }
+
+    output
+}
+
+// Input of a wrong length and a final block indicator flag other than 0 or 1 are errors
+pub fn run_blake2f(input: &[u8]) -> Result<[u8; 64], PrecompileError> {
+    run_blake2f_with(input, eip_152::compress)
+}
+
+// Blake2b compression function F, e.g. `eip_152::portable::compress`
+pub type Blake2fCompress = fn(&mut [u64; 8], [u64; 16], [u64; 2], bool, usize);
+
+pub fn run_blake2f_with(input: &[u8], compress: Blake2fCompress) -> Result<[u8; 64], PrecompileError> {
     use std::io::{Cursor, Write};
     use byteorder::{BigEndian, LittleEndian};
     use byteorder::{ReadBytesExt};
-    use eip_152::compress;
 
     const BLAKE2_F_ARG_LEN: usize = 213;
     const PROOF: &str = "Checked the length of the input above; qed";
 
     if input.len() != BLAKE2_F_ARG_LEN {
-        panic!("input length for Blake2 F precompile should be exactly 213 bytes");
+        return Err(PrecompileError::InvalidLength("Invalid input length, must be exactly 213 bytes"));
     }
 
     let mut cursor = Cursor::new(&input);
@@ -61,7 +138,7 @@ pub fn run_blake2f(input: &[u8]) -> [u8; 64] {
             Some(1) => true,
             Some(0) => false,
             _ => {
-                panic!("incorrect final block indicator flag, was: {:?}", input.last());
+                return Err(PrecompileError::InvalidFlag("Invalid final block indicator flag, must be 0 or 1"));
             }
         };
 
@@ -76,15 +153,22 @@ pub fn run_blake2f(input: &[u8]) -> [u8; 64] {
 
     (&mut output[..]).write(&output_buf).unwrap();
 
-    output
+    Ok(output)
 }
 
-pub fn run_bn_add(input: &[u8]) -> [u8; 64] {
+// Input is padded with zeros or cut to 128 bytes as in EIP-196. Invalid points (coordinate not
+// below the field modulus or not on curve) are errors
+pub fn run_bn_add(input: &[u8]) -> Result<[u8; 64], PrecompileError> {
     use bn::{AffineG1};
     use std::io::Write;
 
-    let p1 = helpers::read_point(&input[0..64]).unwrap();
-    let p2 = helpers::read_point(&input[64..128]).unwrap();
+    let len = std::cmp::min(input.len(), 128);
+
+    let mut padded = [0u8; 128];
+    padded[..len].copy_from_slice(&input[..len]);
+
+    let p1 = helpers::read_point(&padded[0..64])?;
+    let p2 = helpers::read_point(&padded[64..128])?;
 
     let mut write_buf = [0u8; 64];
     if let Some(sum) = AffineG1::from_jacobian(p1 + p2) {
@@ -96,15 +180,22 @@ pub fn run_bn_add(input: &[u8]) -> [u8; 64] {
     let mut output = [0u8; 64];
     (&mut output[..]).write(&write_buf).unwrap();
 
-    output
+    Ok(output)
 }
 
-pub fn run_bn_mul(input: &[u8]) -> [u8; 64] {
+// Input is padded with zeros or cut to 96 bytes as in EIP-196. Invalid points (coordinate not
+// below the field modulus or not on curve) are errors
+pub fn run_bn_mul(input: &[u8]) -> Result<[u8; 64], PrecompileError> {
     use bn::{AffineG1};
     use std::io::Write;
 
-    let p1 = helpers::read_point(&input[0..64]).unwrap();                 
-    let fr = helpers::read_fr(&input[64..96]).unwrap();
+    let len = std::cmp::min(input.len(), 96);
+
+    let mut padded = [0u8; 96];
+    padded[..len].copy_from_slice(&input[..len]);
+
+    let p1 = helpers::read_point(&padded[0..64])?;
+    let fr = helpers::read_fr(&padded[64..96])?;
 
     let mut write_buf = [0u8; 64];
     if let Some(sum) = AffineG1::from_jacobian(p1 * fr) {
@@ -116,16 +207,22 @@ pub fn run_bn_mul(input: &[u8]) -> [u8; 64] {
     let mut output = [0u8; 64];
     (&mut output[..]).write(&write_buf).unwrap();
 
-    output
+    Ok(output)
 }
 
-pub fn run_bn_pair(input: &[u8]) -> [u8; 32] {
-    use bn::{AffineG1, AffineG2, G1, G2, Group, Fq, Fq2, pairing_batch, Gt};
+// Input length must be a multiple of 192. Invalid points are errors, as well as G2 points outside
+// of the prime order subgroup
+pub fn run_bn_pair(input: &[u8]) -> Result<[u8; 32], PrecompileError> {
+    use bn::{AffineG1, G1, Group, Fq, pairing_batch, Gt};
     use ethereum_types::U256;
     use std::io::Write;
 
     let mut output = [0u8; 32];
 
+    if input.len() % 192 != 0 {
+        return Err(PrecompileError::InvalidPairingLength("Invalid input length, must be multiple of 192 (3 * (32*2))"));
+    }
+
     let ret_val = if input.is_empty() {
         U256::one()
     } else {
@@ -134,34 +231,16 @@ pub fn run_bn_pair(input: &[u8]) -> [u8; 32] {
         let mut vals = Vec::new();
         for idx in 0..elements {
             let a_x = Fq::from_slice(&input[idx*192..idx*192+32])
-                .map_err(|_| "Invalid a argument x coordinate").unwrap();
+                .map_err(|_| PrecompileError::NotBelowModulus("Invalid a argument x coordinate"))?;
 
             let a_y = Fq::from_slice(&input[idx*192+32..idx*192+64])
-                .map_err(|_| "Invalid a argument y coordinate").unwrap();
-
-            let b_a_y = Fq::from_slice(&input[idx*192+64..idx*192+96])
-                .map_err(|_| "Invalid b argument imaginary coeff x coordinate").unwrap();
-
-            let b_a_x = Fq::from_slice(&input[idx*192+96..idx*192+128])
-                .map_err(|_| "Invalid b argument imaginary coeff y coordinate").unwrap();
-
-            let b_b_y = Fq::from_slice(&input[idx*192+128..idx*192+160])
-                .map_err(|_| "Invalid b argument real coeff x coordinate").unwrap();
+                .map_err(|_| PrecompileError::NotBelowModulus("Invalid a argument y coordinate"))?;
 
-            let b_b_x = Fq::from_slice(&input[idx*192+160..idx*192+192])
-                .map_err(|_| "Invalid b argument real coeff y coordinate").unwrap();
-
-            let b_a = Fq2::new(b_a_x, b_a_y);
-            let b_b = Fq2::new(b_b_x, b_b_y);
-            let b = if b_a.is_zero() && b_b.is_zero() {
-                G2::zero()
-            } else {
-                G2::from(AffineG2::new(b_a, b_b).map_err(|_| "Invalid b argument - not on curve").unwrap())
-            };
+            let b = helpers::read_g2_point(&input[idx*192+64..idx*192+192])?;
             let a = if a_x.is_zero() && a_y.is_zero() {
                 G1::zero()
             } else {
-                G1::from(AffineG1::new(a_x, a_y).map_err(|_| "Invalid a argument - not on curve").unwrap())
+                G1::from(AffineG1::new(a_x, a_y).map_err(|_| PrecompileError::NotOnCurve("Invalid a argument - not on curve"))?)
             };
             vals.push((a, b));
         };
@@ -179,5 +258,5 @@ pub fn run_bn_pair(input: &[u8]) -> [u8; 32] {
     ret_val.to_big_endian(&mut buf);
     (&mut output[..]).write(&buf).unwrap();
 
-    output
-}
\ No newline at end of file
+    Ok(output)
+}
@@ -0,0 +1,396 @@
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Serialize, Deserialize};
+
+use super::generator::DEFAULT_RNG_SEED;
+use super::measurements::{self, TimingStats};
+use super::pricers::{self, Pricer};
+use super::{helpers, runners};
+
+// Search works on a "genome" of raw bytes that is mapped into a valid precompile input for a given
+// parameter, so any byte-level mutation of the genome still produces an input that the precompile
+// accepts (e.g. for BN curve operations the genome contains scalars, not the points themselves)
+pub struct SearchTarget {
+    pub name: &'static str,
+    pub params: Vec<u64>,
+    pub schedules: Vec<(&'static str, Pricer)>,
+    pub genome_len: fn(u64) -> usize,
+    pub encode: fn(u64, &[u8]) -> Vec<u8>,
+    pub run: fn(&[u8]) -> Vec<u8>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SearchParameters {
+    // random genomes to start hill climbing from, per parameter
+    pub num_restarts: usize,
+    // mutations tried per restart
+    pub num_iterations: usize,
+    // measurements of every candidate
+    pub num_tries_per_candidate: usize,
+    // measurements of every found input before it's written
+    pub num_tries_per_result: usize,
+    // number of worst inputs written per schedule
+    pub num_worst: usize,
+    pub rng_seed: [u8; 16]
+}
+
+impl SearchParameters {
+    pub fn new(num_restarts: usize, num_iterations: usize, num_tries_per_candidate: usize, num_worst: usize) -> Self {
+        Self {
+            num_restarts,
+            num_iterations,
+            num_tries_per_candidate,
+            num_tries_per_result: num_tries_per_candidate * 10,
+            num_worst,
+            rng_seed: DEFAULT_RNG_SEED
+        }
+    }
+}
+
+// Slowest input found for a single parameter
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub scalar: u64,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+    pub timing: TimingStats
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WorstCase {
+    pub precompile: String,
+    pub schedule: String,
+    pub scalar: u64,
+    pub gas: u64,
+    pub median_ns: u128,
+    pub mean_ns: u128,
+    pub ns_per_gas: f64,
+    pub input: String,
+    pub output: String
+}
+
+pub fn search_target(name: &str) -> Option<SearchTarget> {
+    let target = match name {
+        "sha256" => SearchTarget {
+            name: "sha256",
+            params: (0..=256).step_by(8).collect(),
+            schedules: vec![("current", pricers::current_sha256_pricer()), ("proposed", pricers::proposed_sha256_pricer())],
+            genome_len: |len| len as usize,
+            encode: |_, genome| genome.to_vec(),
+            run: |input| runners::run_sha256(input).to_vec()
+        },
+        "ripemd" => SearchTarget {
+            name: "ripemd",
+            params: (0..=256).step_by(8).collect(),
+            schedules: vec![("current", pricers::current_ripemd_pricer()), ("proposed", pricers::proposed_ripemd_pricer())],
+            genome_len: |len| len as usize,
+            encode: |_, genome| genome.to_vec(),
+            run: |input| runners::run_ripemd160(input).to_vec()
+        },
+        "blake2f" => SearchTarget {
+            name: "blake2f",
+            params: vec![1, 2, 3, 4, 8, 16, 32, 64, 128],
+            schedules: vec![("current", pricers::blake2f_pricer()), ("proposed", pricers::blake2f_pricer())],
+            genome_len: |_| 209,
+            encode: encode_blake2f_genome,
+            run: |input| runners::run_blake2f(input).map(|o| o.to_vec()).unwrap_or_default()
+        },
+        "bnadd" => SearchTarget {
+            name: "bnadd",
+            params: vec![0],
+            schedules: vec![("current", pricers::current_bnadd_pricer()), ("proposed", pricers::proposed_bnadd_pricer())],
+            genome_len: |_| 64,
+            encode: |_, genome| {
+                let mut input = vec![];
+                input.extend_from_slice(&encode_g1_from_scalar(&genome[0..32]));
+                input.extend_from_slice(&encode_g1_from_scalar(&genome[32..64]));
+
+                input
+            },
+            run: |input| runners::run_bn_add(input).map(|o| o.to_vec()).unwrap_or_default()
+        },
+        "bnmul" => SearchTarget {
+            name: "bnmul",
+            params: vec![0],
+            schedules: vec![("current", pricers::current_bnmul_pricer()), ("proposed", pricers::proposed_bnmul_pricer())],
+            genome_len: |_| 64,
+            encode: |_, genome| {
+                let mut input = vec![];
+                input.extend_from_slice(&encode_g1_from_scalar(&genome[0..32]));
+                input.extend_from_slice(&genome[32..64]);
+
+                input
+            },
+            run: |input| runners::run_bn_mul(input).map(|o| o.to_vec()).unwrap_or_default()
+        },
+        "bnpair" => SearchTarget {
+            name: "bnpair",
+            params: vec![1, 2, 4, 8],
+            schedules: vec![("current", pricers::bnpair_pricer()), ("proposed", pricers::bnpair_pricer())],
+            genome_len: |pairs| (pairs as usize) * 64,
+            encode: encode_bnpair_genome,
+            run: |input| runners::run_bn_pair(input).map(|o| o.to_vec()).unwrap_or_default()
+        },
+        _ => {
+            return None;
+        }
+    };
+
+    Some(target)
+}
+
+// Hill climbing on the median running time. Every restart begins from a random genome and
+// accepts a mutation only if it makes the input slower
+pub fn search_worst_for_param<R: Rng>(target: &SearchTarget, scalar: u64, params: &SearchParameters, rng: &mut R) -> SearchResult {
+    let genome_len = (target.genome_len)(scalar);
+    let measure = |genome: &[u8], num_tries: usize| -> (Vec<u8>, Vec<u8>, TimingStats) {
+        let input = (target.encode)(scalar, genome);
+        let output = (target.run)(&input);
+        let runnable = || (target.run)(&input);
+        let checker = |r: Vec<u8>| r == output;
+        let samples = measurements::measure_samples_with_validity(&runnable, &checker, num_tries, None);
+
+        (input, output, TimingStats::from_samples(&samples))
+    };
+
+    let mut best_genome = vec![0u8; genome_len];
+    let mut best_median = 0u128;
+
+    for _ in 0..std::cmp::max(params.num_restarts, 1) {
+        let mut genome = vec![0u8; genome_len];
+        rng.fill_bytes(&mut genome);
+        let (_, _, timing) = measure(&genome, params.num_tries_per_candidate);
+        let mut median = timing.median_ns;
+
+        for _ in 0..params.num_iterations {
+            if genome_len == 0 {
+                break;
+            }
+            let mut candidate = genome.clone();
+            mutate(&mut candidate, rng);
+            let (_, _, timing) = measure(&candidate, params.num_tries_per_candidate);
+            if timing.median_ns > median {
+                genome = candidate;
+                median = timing.median_ns;
+            }
+        }
+
+        if median >= best_median {
+            best_genome = genome;
+            best_median = median;
+        }
+    }
+
+    let (input, output, timing) = measure(&best_genome, params.num_tries_per_result);
+
+    SearchResult {
+        scalar,
+        input,
+        output,
+        timing
+    }
+}
+
+pub fn search_worst_cases(target: &SearchTarget, params: &SearchParameters) -> Vec<SearchResult> {
+    let mut rng = XorShiftRng::from_seed(params.rng_seed);
+
+    target.params.iter().map(|&scalar| {
+        let result = search_worst_for_param(target, scalar, params, &mut rng);
+        println!("Slowest input for {} with parameter {}: median {} ns", target.name, scalar, result.timing.median_ns);
+
+        result
+    }).collect()
+}
+
+// Orders found inputs by running time per unit of gas of every schedule, slowest first
+pub fn rank_worst_cases(target: &SearchTarget, results: &[SearchResult], num_worst: usize) -> Vec<(String, Vec<WorstCase>)> {
+    target.schedules.iter().map(|(schedule, pricer)| {
+        let mut cases: Vec<WorstCase> = results.iter().map(|r| {
+            let gas = pricer.price(r.scalar);
+            let ns_per_gas = if gas == 0 { f64::INFINITY } else { (r.timing.median_ns as f64) / (gas as f64) };
+
+            WorstCase {
+                precompile: target.name.to_string(),
+                schedule: schedule.to_string(),
+                scalar: r.scalar,
+                gas,
+                median_ns: r.timing.median_ns,
+                mean_ns: r.timing.mean_ns,
+                ns_per_gas,
+                input: hex::encode(&r.input),
+                output: hex::encode(&r.output)
+            }
+        }).collect();
+        cases.sort_by(|a, b| b.ns_per_gas.partial_cmp(&a.ns_per_gas).unwrap_or(std::cmp::Ordering::Equal));
+        cases.truncate(num_worst);
+
+        (schedule.to_string(), cases)
+    }).collect()
+}
+
+// Worst cases are written as `{base_path}/{schedule}/input_param_scalar_{scalar}_gas_{gas}.csv`
+// in the same format as the regular vectors, and with timings into `{base_path}/{schedule}/worst.json`
+pub fn write_worst_cases(base_path: &str, ranked: &[(String, Vec<WorstCase>)]) -> std::io::Result<()> {
+    for (schedule, cases) in ranked.iter() {
+        let dir = format!("{}/{}", base_path, schedule);
+
+        let mut per_file: std::collections::BTreeMap<(u64, u64), Vec<&WorstCase>> = std::collections::BTreeMap::new();
+        for c in cases.iter() {
+            per_file.entry((c.scalar, c.gas)).or_default().push(c);
+        }
+
+        for ((scalar, gas), cases) in per_file.into_iter() {
+            let path = format!("{}/input_param_scalar_{}_gas_{}.csv", dir, scalar, gas);
+            helpers::write_atomically(&path, |file| {
+                let mut writer = csv::Writer::from_writer(file);
+                for c in cases.iter() {
+                    writer.write_record([&c.input, &c.output])?;
+                }
+
+                writer.flush()
+            })?;
+        }
+
+        helpers::write_atomically(&format!("{}/worst.json", dir), |file| {
+            serde_json::to_writer_pretty(file, cases)?;
+
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
+fn mutate<R: Rng>(genome: &mut [u8], rng: &mut R) {
+    let num_mutations = rng.gen_range(1, 5);
+    for _ in 0..num_mutations {
+        let position = rng.gen_range(0, genome.len());
+        match rng.gen_range(0, 3) {
+            0 => genome[position] ^= 1u8 << rng.gen_range(0, 8),
+            1 => genome[position] = rng.gen(),
+            _ => genome[position] = if rng.gen() { 0xff } else { 0x00 }
+        }
+    }
+}
+
+fn encode_blake2f_genome(rounds: u64, genome: &[u8]) -> Vec<u8> {
+    let mut input = (rounds as u32).to_be_bytes().to_vec();
+    input.extend_from_slice(genome);
+    let last = input.len() - 1;
+    input[last] &= 1u8;
+
+    input
+}
+
+fn encode_bnpair_genome(pairs: u64, genome: &[u8]) -> Vec<u8> {
+    use bn::{Group, AffineG2};
+
+    let mut input = vec![];
+    for pair in 0..(pairs as usize) {
+        let offset = pair * 64;
+        input.extend_from_slice(&encode_g1_from_scalar(&genome[offset..(offset + 32)]));
+
+        let fr = scalar_from_genome(&genome[(offset + 32)..(offset + 64)]);
+        let p2 = AffineG2::from_jacobian(bn::G2::one() * fr).expect("scalar is non-zero");
+        let mut encoding = [0u8; 128];
+        p2.x().imaginary().to_big_endian(&mut encoding[0..32]).expect("Cannot fail since 0..32 is 32-byte length");
+        p2.x().real().to_big_endian(&mut encoding[32..64]).expect("Cannot fail since 32..64 is 32-byte length");
+        p2.y().imaginary().to_big_endian(&mut encoding[64..96]).expect("Cannot fail since 64..96 is 32-byte length");
+        p2.y().real().to_big_endian(&mut encoding[96..128]).expect("Cannot fail since 96..128 is 32-byte length");
+        input.extend_from_slice(&encoding);
+    }
+
+    input
+}
+
+fn encode_g1_from_scalar(genome: &[u8]) -> [u8; 64] {
+    use bn::{Group, AffineG1};
+
+    let fr = scalar_from_genome(genome);
+    let p = AffineG1::from_jacobian(bn::G1::one() * fr).expect("scalar is non-zero");
+
+    helpers::encode_g1_point(p)
+}
+
+// Clearing the top byte keeps the scalar below the group order, and zero is replaced with one,
+// so the resulting point is never at infinity
+fn scalar_from_genome(genome: &[u8]) -> bn::Fr {
+    let mut buffer = [0u8; 32];
+    buffer.copy_from_slice(&genome[0..32]);
+    buffer[0] = 0;
+    if buffer == [0u8; 32] {
+        buffer[31] = 1;
+    }
+
+    helpers::read_fr(&buffer).expect("scalar is below the group order")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn do_search(name: &str) {
+        // kept apart from the regular vectors, so they are not mixed into manifests and timing results
+        let base_path = format!("./vectors/worst/{}", name);
+        let target = search_target(name).expect("search target must exist");
+
+        let params = SearchParameters::new(
+            env_or("BENCH_SEARCH_RESTARTS", 3),
+            env_or("BENCH_SEARCH_ITERATIONS", 200),
+            env_or("BENCH_SEARCH_TRIES", 100),
+            env_or("BENCH_SEARCH_WORST", 5)
+        );
+
+        let results = search_worst_cases(&target, &params);
+        let ranked = rank_worst_cases(&target, &results, params.num_worst);
+        for (schedule, cases) in ranked.iter() {
+            for c in cases.iter() {
+                println!("{} schedule: parameter {} costs {} gas and runs {} ns, {:.2} ns per gas", schedule, c.scalar, c.gas, c.median_ns, c.ns_per_gas);
+            }
+        }
+
+        write_worst_cases(&base_path, &ranked).expect("worst cases must be written");
+    }
+
+    fn env_or(name: &str, default: usize) -> usize {
+        std::env::var(name).ok()
+            .map(|v| v.parse::<usize>().unwrap_or_else(|_| panic!("{} must be an integer", name)))
+            .unwrap_or(default)
+    }
+
+    #[test]
+    #[ignore]
+    fn search_worst_sha256() {
+        do_search("sha256");
+    }
+
+    #[test]
+    #[ignore]
+    fn search_worst_ripemd() {
+        do_search("ripemd");
+    }
+
+    #[test]
+    #[ignore]
+    fn search_worst_blake2f() {
+        do_search("blake2f");
+    }
+
+    #[test]
+    #[ignore]
+    fn search_worst_bnadd() {
+        do_search("bnadd");
+    }
+
+    #[test]
+    #[ignore]
+    fn search_worst_bnmul() {
+        do_search("bnmul");
+    }
+
+    #[test]
+    #[ignore]
+    fn search_worst_bnpair() {
+        do_search("bnpair");
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct GethJsonFormat {
@@ -7,7 +8,9 @@ pub struct GethJsonFormat {
     #[serde(rename = "Expected")]
     output: String,
     #[serde(rename = "Name")]
-    name: String
+    name: String,
+    #[serde(rename = "Seed", skip_serializing_if = "Option::is_none", default)]
+    seed: Option<String>
 }
 
 impl GethJsonFormat {
@@ -15,7 +18,82 @@ impl GethJsonFormat {
         Self {
             input: hex::encode(input),
             output: hex::encode(output),
-            name
+            name,
+            seed: None
         }
     }
-}
\ No newline at end of file
+
+    pub fn with_seed(mut self, seed: &[u8]) -> Self {
+        self.seed = Some(hex::encode(seed));
+
+        self
+    }
+}
+
+// Invalid input with the error it must be rejected with
+#[derive(Clone, Deserialize, Serialize)]
+pub struct GethFailJsonFormat {
+    #[serde(rename = "Input")]
+    input: String,
+    #[serde(rename = "ExpectedError")]
+    expected_error: String,
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Seed", skip_serializing_if = "Option::is_none", default)]
+    seed: Option<String>
+}
+
+impl GethFailJsonFormat {
+    pub fn new_from_data_and_name(input: &[u8], expected_error: &str, name: String) -> Self {
+        Self {
+            input: hex::encode(input),
+            expected_error: expected_error.to_string(),
+            name,
+            seed: None
+        }
+    }
+
+    pub fn with_seed(mut self, seed: &[u8]) -> Self {
+        self.seed = Some(hex::encode(seed));
+
+        self
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateTestEnv {
+    pub current_coinbase: String,
+    pub current_difficulty: String,
+    pub current_gas_limit: String,
+    pub current_number: String,
+    pub current_timestamp: String,
+    pub current_base_fee: String
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct StateTestAccount {
+    pub balance: String,
+    pub code: String,
+    pub nonce: String,
+    pub storage: BTreeMap<String, String>
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct StateTestExpectation {
+    pub indexes: StateTestIndexes,
+    pub network: Vec<String>,
+    pub result: BTreeMap<String, StateTestAccountExpectation>
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct StateTestIndexes {
+    pub data: i64,
+    pub gas: i64,
+    pub value: i64
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct StateTestAccountExpectation {
+    pub storage: BTreeMap<String, String>
+}
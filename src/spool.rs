@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::runners;
+
+// (family, scalar, current gas, proposed gas)
+pub type SpoolKey = (String, u64, u64, u64);
+
+// Writers of the same measurements share one spool, so every vector is stored on disk only once
+pub type SharedSpool = Rc<RefCell<RecordSpool>>;
+
+static SPOOL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// On-disk append-only storage of deduplicated (input, output, label, seed) records grouped by key.
+// Records for every key are kept as a headerless CSV file of hex-encoded input and output, the label
+// and hex-encoded seed, in the order they were added. Only SHA256 digests of inputs are kept in memory for deduplication.
+// Only the file of the last added key is open, records mostly come key after key and a file
+// of an earlier key is reopened in append mode, so sweeps over many parameters don't run out of file descriptors.
+// Spool lives in the system temporary directory (respects TMPDIR) and is removed on drop.
+pub struct RecordSpool {
+    dir: PathBuf,
+    keys: BTreeSet<SpoolKey>,
+    current: Option<(SpoolKey, csv::Writer<std::fs::File>)>,
+    seen_inputs: HashMap<SpoolKey, HashSet<[u8; 32]>>
+}
+
+impl RecordSpool {
+    pub fn new(name: &str) -> Self {
+        let id = SPOOL_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let dir = std::env::temp_dir().join(format!("bench_precompiles_spool_{}_{}_{}", name, std::process::id(), id));
+
+        Self {
+            dir,
+            keys: BTreeSet::new(),
+            current: None,
+            seen_inputs: HashMap::new()
+        }
+    }
+
+    pub fn shared(name: &str) -> SharedSpool {
+        Rc::new(RefCell::new(Self::new(name)))
+    }
+
+    // Returns `false` if the same input was already added for this key, so writers sharing
+    // the spool can all add the same record
+    pub fn add(&mut self, key: SpoolKey, input: &[u8], output: &[u8], label: &str, seed: &[u8]) -> std::io::Result<bool> {
+        let digest = runners::run_sha256(input);
+        if self.seen_inputs.get(&key).map(|s| s.contains(&digest)).unwrap_or(false) {
+            return Ok(false);
+        }
+
+        let is_current = self.current.as_ref().map(|(k, _)| *k == key).unwrap_or(false);
+        if !is_current {
+            self.flush()?;
+            self.current = None;
+            std::fs::create_dir_all(&self.dir)?;
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path_for_key(&key))?;
+            self.current = Some((key.clone(), csv::Writer::from_writer(file)));
+            self.keys.insert(key.clone());
+        }
+
+        let (_, writer) = self.current.as_mut().expect("writer was opened above");
+        writer.write_record(&[
+            hex::encode(input),
+            hex::encode(output),
+            label.to_string(),
+            hex::encode(seed)
+        ])?;
+
+        self.seen_inputs.entry(key).or_default().insert(digest);
+
+        Ok(true)
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        if let Some((_, writer)) = self.current.as_mut() {
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn keys(&self) -> Vec<SpoolKey> {
+        self.keys.iter().cloned().collect()
+    }
+
+    pub fn path_for_key(&self, key: &SpoolKey) -> PathBuf {
+        let (family, scalar, current_gas, proposed_gas) = key;
+
+        self.dir.join(format!("{}_{}_{}_{}.csv", family, scalar, current_gas, proposed_gas))
+    }
+
+    // Spool must be flushed before reading
+    pub fn for_each_record<F: FnMut(&[u8], &[u8], &str, &[u8]) -> std::io::Result<()>>(&self, key: &SpoolKey, mut f: F) -> std::io::Result<()> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_path(self.path_for_key(key))?;
+
+        for record in reader.records() {
+            let record = record?;
+            let input = decode_hex_field(record.get(0))?;
+            let output = decode_hex_field(record.get(1))?;
+            let label = record.get(2).unwrap_or_default();
+            let seed = decode_hex_field(record.get(3))?;
+            f(&input, &output, label, &seed)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for RecordSpool {
+    fn drop(&mut self) {
+        self.current = None;
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn decode_hex_field(field: Option<&str>) -> std::io::Result<Vec<u8>> {
+    let field = field.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "spool record is truncated"))?;
+
+    hex::decode(field).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+
+use super::serialization::{
+    StateTestEnv,
+    StateTestAccount,
+    StateTestExpectation,
+    StateTestIndexes,
+    StateTestAccountExpectation
+};
+
+pub const SENDER_SECRET_KEY: &str = "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8";
+pub const SENDER_ADDRESS: &str = "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b";
+pub const CALLER_ADDRESS: &str = "0x095e7baea6a6c7c4c2dfeb977efac326af552d87";
+pub const COINBASE_ADDRESS: &str = "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba";
+
+// precompiles are warm from Berlin onwards, so the caller overhead below is only valid there
+pub const DEFAULT_NETWORK: &str = ">=Berlin";
+
+// No released network charges the proposed schedule and filling tools reject unknown fork names,
+// so fillers of the proposed schedule are written only for a network set explicitly, one the
+// client filling them is configured to charge the proposed prices on
+pub const PROPOSED_NETWORK_ENV: &str = "BENCH_PROPOSED_NETWORK";
+
+// `None` if no filler should be written for the schedule
+pub fn network_for_schedule(schedule: &str) -> Option<String> {
+    match schedule {
+        "proposed" => std::env::var(PROPOSED_NETWORK_ENV).ok()
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty()),
+        _ => Some(String::from(DEFAULT_NETWORK))
+    }
+}
+
+// Gas spent by the caller between the two GAS opcodes that surround STATICCALL, excluding
+// the gas consumed by the precompile itself: eight 3 gas opcodes preparing arguments,
+// CALLDATASIZE (2), warm account access (100) and the second GAS (2)
+pub const CALL_OVERHEAD_GAS: u64 = 128;
+
+const STORAGE_SLOT_SUCCESS: u64 = 0;
+const STORAGE_SLOT_GAS_USED: u64 = 1;
+const STORAGE_SLOT_FIRST_OUTPUT_WORD: u64 = 2;
+
+const MIN_BLOCK_GAS_LIMIT: u64 = 30_000_000;
+
+// Receives (label, scheduled gas, input, expected output) of every state test entry
+pub type EntryVisitor<'a> = dyn FnMut(&str, u64, &[u8], &[u8]) -> std::io::Result<()> + 'a;
+
+mod opcodes {
+    pub const STOP: u8 = 0x00;
+    pub const SUB: u8 = 0x03;
+    pub const CALLDATALOAD: u8 = 0x35;
+    pub const CALLDATASIZE: u8 = 0x36;
+    pub const CALLDATACOPY: u8 = 0x37;
+    pub const MLOAD: u8 = 0x51;
+    pub const MSTORE: u8 = 0x52;
+    pub const SSTORE: u8 = 0x55;
+    pub const GAS: u8 = 0x5a;
+    pub const PUSH1: u8 = 0x60;
+    pub const SWAP1: u8 = 0x90;
+    pub const STATICCALL: u8 = 0xfa;
+}
+
+// Caller contract expects calldata as `gas (32 bytes) || precompile input`. It forwards exactly
+// `gas` to the precompile using STATICCALL and stores the success flag into slot 0, gas consumed
+// by the precompile into slot 1 and the returned words into slots 2 and onwards
+pub fn caller_contract_code(precompile_address: u8, output_len: usize) -> Vec<u8> {
+    use self::opcodes::*;
+
+    assert!(output_len <= 0xe0, "output must be addressable with PUSH1");
+    assert_eq!(output_len % 32, 0, "output is stored word by word");
+
+    let mut code = vec![];
+
+    // expand memory for the output upfront, so STATICCALL itself never pays for memory expansion
+    code.extend_from_slice(&[PUSH1, 0x00, PUSH1, output_len as u8, MSTORE]);
+
+    // copy precompile input into memory at offset 0
+    code.extend_from_slice(&[PUSH1, 0x20, CALLDATASIZE, SUB, PUSH1, 0x20, PUSH1, 0x00, CALLDATACOPY]);
+
+    code.push(GAS);
+
+    code.extend_from_slice(&[
+        PUSH1, output_len as u8,
+        PUSH1, 0x00,
+        PUSH1, 0x20, CALLDATASIZE, SUB,
+        PUSH1, 0x00,
+        PUSH1, precompile_address,
+        PUSH1, 0x00, CALLDATALOAD,
+        STATICCALL
+    ]);
+
+    code.push(GAS);
+
+    // stack is [gas_before, success, gas_after]
+    code.extend_from_slice(&[SWAP1, PUSH1, STORAGE_SLOT_SUCCESS as u8, SSTORE]);
+    code.extend_from_slice(&[SWAP1, SUB, PUSH1, CALL_OVERHEAD_GAS as u8, SWAP1, SUB, PUSH1, STORAGE_SLOT_GAS_USED as u8, SSTORE]);
+
+    for word in 0..(output_len / 32) {
+        code.extend_from_slice(&[PUSH1, (word * 32) as u8, MLOAD, PUSH1, (STORAGE_SLOT_FIRST_OUTPUT_WORD as usize + word) as u8, SSTORE]);
+    }
+
+    code.push(STOP);
+
+    code
+}
+
+pub fn encode_calldata(gas: u64, input: &[u8]) -> Vec<u8> {
+    let mut calldata = vec![0u8; 32];
+    calldata[24..32].copy_from_slice(&gas.to_be_bytes());
+    calldata.extend_from_slice(input);
+
+    calldata
+}
+
+pub fn expected_storage(gas: u64, output: &[u8]) -> BTreeMap<String, String> {
+    let mut storage = BTreeMap::new();
+    storage.insert(encode_quantity(STORAGE_SLOT_SUCCESS), encode_quantity(1));
+    storage.insert(encode_quantity(STORAGE_SLOT_GAS_USED), encode_quantity(gas));
+    for (i, word) in output.chunks(32).enumerate() {
+        storage.insert(encode_quantity(STORAGE_SLOT_FIRST_OUTPUT_WORD + i as u64), encode_word(word));
+    }
+
+    storage
+}
+
+// Filler is streamed into `writer` in three passes over the entries, so they never have to be
+// held in memory at once
+pub fn write_filler<W, V>(writer: &mut W, test_name: &str, network: &str, precompile_address: u8, visit_entries: V) -> std::io::Result<()>
+where
+    W: std::io::Write,
+    V: Fn(&mut EntryVisitor) -> std::io::Result<()>
+{
+    let mut output_len = None;
+    let mut tx_gas_limit = 0u64;
+    visit_entries(&mut |_, gas, input, output| {
+        let expected_len = *output_len.get_or_insert(output.len());
+        if expected_len != output.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "all outputs in a single test must have the same length"));
+        }
+        let calldata_len = 32 + input.len();
+        tx_gas_limit = std::cmp::max(tx_gas_limit, required_transaction_gas(gas, calldata_len, expected_len));
+
+        Ok(())
+    })?;
+
+    let output_len = output_len.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "state test must contain at least one entry"))?;
+    let code = caller_contract_code(precompile_address, output_len);
+
+    let env = StateTestEnv {
+        current_coinbase: COINBASE_ADDRESS.to_string(),
+        current_difficulty: encode_quantity(0x020000),
+        current_gas_limit: encode_quantity(std::cmp::max(tx_gas_limit, MIN_BLOCK_GAS_LIMIT)),
+        current_number: encode_quantity(1),
+        current_timestamp: encode_quantity(1000),
+        current_base_fee: encode_quantity(10),
+    };
+
+    let mut pre = BTreeMap::new();
+    pre.insert(CALLER_ADDRESS.to_string(), StateTestAccount {
+        balance: encode_quantity(0),
+        code: format!("0x{}", hex::encode(&code)),
+        nonce: encode_quantity(0),
+        storage: BTreeMap::new()
+    });
+    pre.insert(SENDER_ADDRESS.to_string(), StateTestAccount {
+        balance: String::from("0xffffffffffffffffffffffffffffffff"),
+        code: String::from("0x"),
+        nonce: encode_quantity(0),
+        storage: BTreeMap::new()
+    });
+
+    write!(writer, "{{")?;
+    serde_json::to_writer(&mut *writer, test_name)?;
+    write!(writer, ":{{\"env\":")?;
+    serde_json::to_writer(&mut *writer, &env)?;
+    write!(writer, ",\"pre\":")?;
+    serde_json::to_writer(&mut *writer, &pre)?;
+    write!(writer, ",\"transaction\":{{\"data\":[")?;
+
+    let mut first = true;
+    visit_entries(&mut |label, gas, input, _| {
+        if !first {
+            write!(writer, ",")?;
+        }
+        first = false;
+        let calldata = encode_calldata(gas, input);
+        serde_json::to_writer(&mut *writer, &format!(":label {} :raw 0x{}", label, hex::encode(&calldata)))?;
+
+        Ok(())
+    })?;
+
+    write!(writer, "],\"gasLimit\":")?;
+    serde_json::to_writer(&mut *writer, &vec![encode_quantity(tx_gas_limit)])?;
+    write!(writer, ",\"gasPrice\":")?;
+    serde_json::to_writer(&mut *writer, &encode_quantity(10))?;
+    write!(writer, ",\"nonce\":")?;
+    serde_json::to_writer(&mut *writer, &encode_quantity(0))?;
+    write!(writer, ",\"secretKey\":")?;
+    serde_json::to_writer(&mut *writer, SENDER_SECRET_KEY)?;
+    write!(writer, ",\"to\":")?;
+    serde_json::to_writer(&mut *writer, CALLER_ADDRESS)?;
+    write!(writer, ",\"value\":")?;
+    serde_json::to_writer(&mut *writer, &vec![encode_quantity(0)])?;
+    write!(writer, "}},\"expect\":[")?;
+
+    let mut index = 0i64;
+    visit_entries(&mut |_, gas, _, output| {
+        if index != 0 {
+            write!(writer, ",")?;
+        }
+
+        let mut result = BTreeMap::new();
+        result.insert(CALLER_ADDRESS.to_string(), StateTestAccountExpectation {
+            storage: expected_storage(gas, output)
+        });
+
+        let expectation = StateTestExpectation {
+            indexes: StateTestIndexes {
+                data: index,
+                gas: -1,
+                value: -1
+            },
+            network: vec![network.to_string()],
+            result
+        };
+        serde_json::to_writer(&mut *writer, &expectation)?;
+        index += 1;
+
+        Ok(())
+    })?;
+
+    write!(writer, "]}}}}")?;
+
+    Ok(())
+}
+
+fn required_transaction_gas(precompile_gas: u64, calldata_len: usize, output_len: usize) -> u64 {
+    const TX_BASE_GAS: u64 = 21_000;
+    const CALLDATA_BYTE_GAS: u64 = 16;
+    const CALLER_EXECUTION_ALLOWANCE: u64 = 10_000;
+    const SSTORE_ALLOWANCE: u64 = 22_100;
+
+    let num_slots = STORAGE_SLOT_FIRST_OUTPUT_WORD + (output_len / 32) as u64;
+
+    // STATICCALL can only forward 63/64 of the available gas
+    TX_BASE_GAS
+        + CALLDATA_BYTE_GAS * (calldata_len as u64)
+        + CALLER_EXECUTION_ALLOWANCE
+        + SSTORE_ALLOWANCE * num_slots
+        + precompile_gas + precompile_gas / 63 + 1
+}
+
+fn encode_quantity(value: u64) -> String {
+    format!("0x{:02x}", value)
+}
+
+fn encode_word(word: &[u8]) -> String {
+    let encoding = hex::encode(word);
+    let trimmed = encoding.trim_start_matches('0');
+    if trimmed.is_empty() {
+        String::from("0x00")
+    } else {
+        format!("0x{}", trimmed)
+    }
+}
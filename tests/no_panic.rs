@@ -0,0 +1,187 @@
+// Runners are fed arbitrary inputs, e.g. while fuzzing, so they must reject any input without a
+// panic, in bounded time and with bounded allocation
+use bench_precompiles::runners::*;
+use bench_precompiles::{fuzzing, helpers};
+use proptest::prelude::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+// Several times the longest fixed size input
+const MAX_INPUT_LEN: usize = 4096;
+// Blake2f takes as long as its input asks for, which only gas bounds
+const MAX_BLAKE2F_ROUNDS: u32 = 1024;
+// Generous, as `bn` is optimized in test builds (see Cargo.toml), a runner that hangs takes far longer
+const TIME_BUDGET: Duration = Duration::from_secs(2);
+// Beyond copies of the input, runners only need fixed size buffers
+const BASE_ALLOCATION_BUDGET: usize = 64 * 1024;
+const ALLOCATION_BUDGET_PER_INPUT_BYTE: usize = 16;
+// Pairings of valid points are slow in a debug build
+const MAX_PAIRS: usize = 3;
+
+const G1_GENERATOR: &str = "00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000002";
+// Imaginary coefficients first, as in pairing inputs
+const G2_GENERATOR: &str = "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c21800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa";
+
+// Counts bytes allocated by the current thread, as tests run in parallel. Integration tests are
+// built as separate binaries, so it only replaces the allocator of this suite
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED_BYTES: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation(size: usize) {
+    let _ = ALLOCATED_BYTES.try_with(|allocated| allocated.set(allocated.get().saturating_add(size)));
+}
+
+fn allocated_bytes() -> usize {
+    ALLOCATED_BYTES.try_with(|allocated| allocated.get()).unwrap_or(0)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// A panic fails the case by itself
+fn check_runner<T, F: Fn(&[u8]) -> T>(runner: F, input: &[u8]) -> Result<(), TestCaseError> {
+    let allocated_before = allocated_bytes();
+    let started = Instant::now();
+    let result = runner(input);
+    let elapsed = started.elapsed();
+    let allocated = allocated_bytes() - allocated_before;
+    drop(result);
+
+    prop_assert!(elapsed <= TIME_BUDGET, "took {:?} for {} bytes of input", elapsed, input.len());
+    let allocation_budget = BASE_ALLOCATION_BUDGET + ALLOCATION_BUDGET_PER_INPUT_BYTE * input.len();
+    prop_assert!(allocated <= allocation_budget, "allocated {} bytes for {} bytes of input", allocated, input.len());
+
+    Ok(())
+}
+
+fn any_input() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), 0..MAX_INPUT_LEN)
+}
+
+// Arbitrary inputs of an exact length get past length checks
+fn input_of_len(len: usize) -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), len)
+}
+
+// Cut at an arbitrary length, which the untruncated input has as well
+fn truncated<S: Strategy<Value = Vec<u8>>>(input: S) -> impl Strategy<Value = Vec<u8>> {
+    (input, any::<prop::sample::Index>()).prop_map(|(mut input, i)| {
+        let len = i.index(input.len() + 1);
+        input.truncate(len);
+
+        input
+    })
+}
+
+fn blake2f_input() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![any_input(), input_of_len(213)]
+        .prop_map(|input| fuzzing::bound_input("blake2f", input, MAX_BLAKE2F_ROUNDS))
+}
+
+// Random coordinates are almost never valid, so that a runner would reject the first point of
+// an input and never read the rest of it. Generators and the point at infinity get past point
+// checks
+fn g1_point() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        Just(vec![0u8; 64]),
+        Just(hex::decode(G1_GENERATOR).unwrap()),
+        input_of_len(64)
+    ]
+}
+
+fn g2_point() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        Just(vec![0u8; 128]),
+        Just(hex::decode(G2_GENERATOR).unwrap()),
+        input_of_len(128)
+    ]
+}
+
+fn bn_add_input() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![any_input(), truncated((g1_point(), g1_point()).prop_map(|(a, b)| [a, b].concat()))]
+}
+
+fn bn_mul_input() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![any_input(), truncated((g1_point(), input_of_len(32)).prop_map(|(p, s)| [p, s].concat()))]
+}
+
+fn pairs() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec((g1_point(), g2_point()), 0..MAX_PAIRS)
+        .prop_map(|pairs| pairs.into_iter().flat_map(|(a, b)| [a, b].concat()).collect())
+}
+
+fn bn_pair_input() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![any_input(), pairs(), truncated(pairs())]
+}
+
+proptest! {
+    #[test]
+    fn sha256_does_not_panic(input in any_input()) {
+        check_runner(run_sha256, &input)?;
+    }
+
+    #[test]
+    fn ripemd160_does_not_panic(input in any_input()) {
+        check_runner(run_ripemd160, &input)?;
+    }
+
+    #[test]
+    fn ecrecover_does_not_panic(input in prop_oneof![any_input(), input_of_len(128)]) {
+        check_runner(run_ecrecover, &input)?;
+    }
+
+    #[test]
+    fn blake2f_does_not_panic(input in blake2f_input()) {
+        check_runner(run_blake2f, &input)?;
+    }
+
+    #[test]
+    fn bn_add_does_not_panic(input in bn_add_input()) {
+        check_runner(run_bn_add, &input)?;
+    }
+
+    #[test]
+    fn bn_mul_does_not_panic(input in bn_mul_input()) {
+        check_runner(run_bn_mul, &input)?;
+    }
+
+    #[test]
+    fn bn_pair_does_not_panic(input in bn_pair_input()) {
+        check_runner(run_bn_pair, &input)?;
+    }
+
+    #[test]
+    fn read_point_does_not_panic(input in prop_oneof![prop::collection::vec(any::<u8>(), 0..256), g1_point()]) {
+        check_runner(helpers::read_point, &input)?;
+    }
+
+    #[test]
+    fn read_fr_does_not_panic(input in prop_oneof![prop::collection::vec(any::<u8>(), 0..256), input_of_len(32)]) {
+        check_runner(helpers::read_fr, &input)?;
+    }
+
+    #[test]
+    fn read_g2_point_does_not_panic(input in prop_oneof![prop::collection::vec(any::<u8>(), 0..256), g2_point()]) {
+        check_runner(helpers::read_g2_point, &input)?;
+    }
+}